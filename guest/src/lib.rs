@@ -1,13 +1,19 @@
-use revm_context::{BlockEnv, CfgEnv, Evm, Journal, LocalContext, TxEnv, result::EVMError};
+use revm_context::{
+    BlockEnv, CfgEnv, Evm, Journal, LocalContext, TxEnv,
+    result::{EVMError, ExecutionResult},
+};
 use revm_handler::{EthFrame, MainnetHandler};
 use serde::{Deserialize, Serialize};
 use std::{cell::RefCell, convert::Infallible, marker::PhantomData, rc::Rc};
 
 pub use revm_bytecode::{Bytecode, OpCode};
 pub use revm_database::{Cache, CacheDB, DbAccount, EmptyDB};
-pub use revm_handler::{EthPrecompiles, Handler, ItemOrResult, instructions::EthInstructions};
+pub use revm_handler::{
+    EthPrecompiles, FrameResult, Handler, ItemOrResult, PrecompileProvider,
+    instructions::EthInstructions,
+};
 pub use revm_interpreter::{
-    CallInput, InputsImpl, SharedMemory, Stack,
+    CallInput, InputsImpl, InterpreterResult, SharedMemory, Stack,
     interpreter::{EthInterpreter, ExtBytecode},
     interpreter_types::ReturnData,
 };
@@ -22,9 +28,9 @@ pub use revm_interpreter as interpreter;
 pub use revm_primitives as primitives;
 pub use revm_state as state;
 
-pub type EthFrameT = EthFrame<EvmT, EvmErrorT, EthInterpreter>;
+pub type EthFrameT<P = EthPrecompiles> = EthFrame<EvmT<P>, EvmErrorT, EthInterpreter>;
 pub type EvmErrorT = EVMError<Infallible>;
-pub type EvmT = Evm<ContextT, (), EthInstructionsT, EthPrecompiles>;
+pub type EvmT<P = EthPrecompiles> = Evm<ContextT, (), EthInstructionsT, P>;
 pub type EthInstructionsT = EthInstructions<EthInterpreter, ContextT>;
 pub type ContextT = revm_context::Context<
     BlockEnv,
@@ -101,3 +107,89 @@ impl ContextBuilder {
         ctx
     }
 }
+
+/// Executes the interpreter as the first frame of the transaction described by the context
+/// builder, returning the result of that frame.
+pub fn execute(
+    spec_id: SpecId,
+    interpreter: InterpreterT,
+    context_builder: &ContextBuilder,
+    instructions: EthInstructionsT,
+) -> FrameResult {
+    execute_with_precompiles(
+        spec_id,
+        interpreter,
+        context_builder,
+        instructions,
+        EthPrecompiles::default(),
+    )
+}
+
+/// Same as [`execute`], with the precompiles provided by `precompiles`.
+pub fn execute_with_precompiles<P>(
+    spec_id: SpecId,
+    interpreter: InterpreterT,
+    context_builder: &ContextBuilder,
+    instructions: EthInstructionsT,
+    precompiles: P,
+) -> FrameResult
+where
+    P: PrecompileProvider<ContextT, Output = InterpreterResult>,
+{
+    let context = context_builder.build(spec_id);
+    let mut evm = EvmT::new(context, instructions, precompiles);
+
+    let mut handler = MainnetHandler::<EvmT<P>, EvmErrorT, EthFrameT<P>> {
+        _phantom: PhantomData,
+    };
+
+    let first_frame_input = handler.first_frame_input(&mut evm, u64::MAX).unwrap();
+    let first_frame = handler
+        .first_frame_init(&mut evm, first_frame_input)
+        .unwrap();
+
+    let mut frame_result = match first_frame {
+        ItemOrResult::Item(mut frame) => {
+            frame.interpreter = interpreter;
+            handler.run_exec_loop(&mut evm, frame).unwrap()
+        }
+        ItemOrResult::Result(_) => unreachable!("case not expected"),
+    };
+    handler
+        .last_frame_result(&mut evm, &mut frame_result)
+        .unwrap();
+    frame_result
+}
+
+/// Executes the whole transaction described by the context builder.
+pub fn execute_transaction(
+    spec_id: SpecId,
+    context_builder: &ContextBuilder,
+    instructions: EthInstructionsT,
+) -> Result<ExecutionResult, EvmErrorT> {
+    execute_transaction_with_precompiles(
+        spec_id,
+        context_builder,
+        instructions,
+        EthPrecompiles::default(),
+    )
+}
+
+/// Same as [`execute_transaction`], with the precompiles provided by `precompiles`.
+pub fn execute_transaction_with_precompiles<P>(
+    spec_id: SpecId,
+    context_builder: &ContextBuilder,
+    instructions: EthInstructionsT,
+    precompiles: P,
+) -> Result<ExecutionResult, EvmErrorT>
+where
+    P: PrecompileProvider<ContextT, Output = InterpreterResult>,
+{
+    let context = context_builder.build(spec_id);
+    let mut evm = EvmT::new(context, instructions, precompiles);
+
+    let mut handler = MainnetHandler::<EvmT<P>, EvmErrorT, EthFrameT<P>> {
+        _phantom: PhantomData,
+    };
+    handler.run(&mut evm)
+}
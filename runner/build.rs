@@ -1,4 +1,5 @@
 use sp1_build::{BuildArgs, build_program_with_args};
+use std::{fs, process::Command};
 
 fn main() {
     build_program_with_args(
@@ -15,4 +16,47 @@ fn main() {
             ..Default::default()
         },
     );
+    build_program_with_args(
+        "../transaction-guest",
+        BuildArgs {
+            output_directory: Some("elf".to_string()),
+            ..Default::default()
+        },
+    );
+
+    let git_revision = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=GIT_REVISION={git_revision}");
+    println!("cargo:rerun-if-changed=../.git/HEAD");
+    println!("cargo:rerun-if-changed=../.git/refs/heads");
+
+    let lock = fs::read_to_string("../Cargo.lock").expect("failed to read Cargo.lock");
+    let crate_versions = lock
+        .split("[[package]]")
+        .filter_map(|package| {
+            let field = |key: &str| {
+                package.lines().find_map(|line| {
+                    line.strip_prefix(key)?
+                        .trim_start()
+                        .strip_prefix('=')?
+                        .trim()
+                        .strip_prefix('"')?
+                        .strip_suffix('"')
+                })
+            };
+            let name = field("name")?;
+            let version = field("version")?;
+            let tracked = name.starts_with("revm-")
+                || matches!(name, "sp1-sdk" | "sp1-core-executor" | "sp1-zkvm");
+            tracked.then(|| format!("{name}={version}"))
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    println!("cargo:rustc-env=CRATE_VERSIONS={crate_versions}");
+    println!("cargo:rerun-if-changed=../Cargo.lock");
 }
@@ -0,0 +1,27 @@
+create table runs (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    git_revision TEXT NOT NULL,
+    seed INTEGER NOT NULL,
+    repeat INTEGER NOT NULL,
+    spec TEXT NOT NULL,
+    backend TEXT NOT NULL,
+    guest_elf_hash BLOB NOT NULL,
+    started_at INTEGER NOT NULL
+);
+
+create table results (
+    run_id INTEGER NOT NULL REFERENCES runs (id),
+    case_key TEXT NOT NULL,
+    name TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    repetition INTEGER NOT NULL,
+    input_size INTEGER NOT NULL,
+    baseline_instruction_count INTEGER NOT NULL,
+    exec_instruction_count INTEGER NOT NULL,
+    baseline_elapsed_ns INTEGER NOT NULL,
+    exec_elapsed_ns INTEGER NOT NULL,
+    instruction_count_consumes_by_other_estimated REAL,
+    PRIMARY KEY (run_id, case_key)
+);
+
+create index results_name on results (name);
@@ -0,0 +1 @@
+alter table runs add column manifest_id TEXT;
@@ -0,0 +1 @@
+alter table results add column opcode_usage TEXT;
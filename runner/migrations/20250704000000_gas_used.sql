@@ -0,0 +1,2 @@
+alter table results add column gas_used INTEGER;
+alter table results add column result TEXT;
@@ -0,0 +1 @@
+alter table results add column seed INTEGER;
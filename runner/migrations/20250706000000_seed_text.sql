@@ -0,0 +1,21 @@
+-- Seeds are u64, an INTEGER column holds those of 2^63 and above as negative numbers. They are
+-- stored as decimal text instead, converting the negative values back to the u64 they encode.
+alter table results add column seed_text TEXT;
+update results set seed_text = case
+    when seed >= 0 then cast(seed as text)
+    else cast((seed + 9223372036854775807 + 1) / 1000000000 + 9223372036
+        + ((seed + 9223372036854775807 + 1) % 1000000000 + 854775808) / 1000000000 as text)
+        || printf('%09d', ((seed + 9223372036854775807 + 1) % 1000000000 + 854775808) % 1000000000)
+end;
+alter table results drop column seed;
+alter table results rename column seed_text to seed;
+
+alter table runs add column seed_text TEXT NOT NULL DEFAULT '';
+update runs set seed_text = case
+    when seed >= 0 then cast(seed as text)
+    else cast((seed + 9223372036854775807 + 1) / 1000000000 + 9223372036
+        + ((seed + 9223372036854775807 + 1) % 1000000000 + 854775808) / 1000000000 as text)
+        || printf('%09d', ((seed + 9223372036854775807 + 1) % 1000000000 + 854775808) % 1000000000)
+end;
+alter table runs drop column seed;
+alter table runs rename column seed_text to seed;
@@ -0,0 +1,28 @@
+-- The native backend can not count instructions without perf counters, those are left NULL
+-- instead of being stored as a measured 0.
+create table results_new (
+    run_id INTEGER NOT NULL REFERENCES runs (id),
+    case_key TEXT NOT NULL,
+    name TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    repetition INTEGER NOT NULL,
+    input_size INTEGER NOT NULL,
+    baseline_instruction_count INTEGER,
+    exec_instruction_count INTEGER,
+    baseline_elapsed_ns INTEGER NOT NULL,
+    exec_elapsed_ns INTEGER NOT NULL,
+    instruction_count_consumes_by_other_estimated REAL,
+    opcode_usage TEXT,
+    gas_used INTEGER,
+    result TEXT,
+    seed TEXT,
+    PRIMARY KEY (run_id, case_key)
+);
+insert into results_new
+select run_id, case_key, name, kind, repetition, input_size,
+    baseline_instruction_count, exec_instruction_count, baseline_elapsed_ns, exec_elapsed_ns,
+    instruction_count_consumes_by_other_estimated, opcode_usage, gas_used, result, seed
+from results;
+drop table results;
+alter table results_new rename to results;
+create index results_name on results (name);
@@ -0,0 +1,56 @@
+use clap::ValueEnum;
+use evm_guest::{ContextBuilder, InterpreterResult, InterpreterT, SpecId};
+use sp1_sdk::ExecutionReport;
+use std::time::Duration;
+
+mod native;
+mod perf;
+mod rv32im;
+mod sp1;
+
+pub(crate) use rv32im::{DEFAULT_CROSS_CHECK_INTERVAL, set_cross_check_interval};
+pub(crate) use sp1::CLIENT;
+
+#[derive(Default, Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum Backend {
+    /// Execute the guest ELF with the SP1 executor and count RISC-V instructions.
+    #[default]
+    Sp1,
+    /// Execute the guest ELF with the built-in rv32im emulator, much faster than the SP1 executor.
+    Rv32im,
+    /// Execute the case on the host CPU, timing it and reading hardware counters when available.
+    Native,
+}
+
+/// What a backend observed while executing a single guest input.
+#[derive(Debug, Clone)]
+pub struct Measurement {
+    /// Instructions retired while executing the input.
+    ///
+    /// For the zkVM backends these are RISC-V instructions of the guest, for the native backend
+    /// these are host instructions read from perf counters, `None` if they are unavailable.
+    pub instruction_count: Option<u64>,
+    /// Wall-clock time spent executing the input.
+    pub elapsed: Duration,
+    /// The full execution report, only available from the SP1 backend.
+    pub report: Option<ExecutionReport>,
+    /// The result of the frame, decoded from the public values of the guest. Only set when
+    /// executing a case, the other guests commit something else.
+    pub result: Option<InterpreterResult>,
+}
+
+impl Backend {
+    /// Executes the case, failing if the guest could not run to completion.
+    pub fn execute(
+        self,
+        spec_id: SpecId,
+        interpreter: &InterpreterT,
+        context_builder: &ContextBuilder,
+    ) -> Result<Measurement, String> {
+        match self {
+            Backend::Sp1 => sp1::execute(spec_id, interpreter, context_builder),
+            Backend::Rv32im => rv32im::execute(spec_id, interpreter, context_builder),
+            Backend::Native => native::execute(spec_id, interpreter, context_builder),
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use crate::backend::{Measurement, perf::InstructionCounter};
+use evm_guest::{
+    ContextBuilder, EthInstructions, InterpreterT, SharedMemory, SpecId, execute as execute_frame,
+};
+use std::{
+    cell::RefCell,
+    hint::black_box,
+    rc::Rc,
+    sync::Once,
+    time::{Duration, Instant},
+};
+
+/// Number of times each input is executed, the fastest run is reported.
+const SAMPLES: usize = 5;
+
+pub(super) fn execute(
+    spec_id: SpecId,
+    interpreter: &InterpreterT,
+    context_builder: &ContextBuilder,
+) -> Result<Measurement, String> {
+    let counter = InstructionCounter::open();
+    if counter.is_none() {
+        static WARN: Once = Once::new();
+        WARN.call_once(|| {
+            eprintln!("perf counters unavailable, native instruction counts are left empty")
+        });
+    }
+
+    let mut instruction_count = counter.as_ref().map(|_| u64::MAX);
+    let mut elapsed = Duration::MAX;
+    let mut result = None;
+    for _ in 0..SAMPLES {
+        let (interpreter, context_builder) = detach(interpreter, context_builder);
+        let instructions = EthInstructions::new_mainnet();
+
+        if let Some(counter) = counter.as_ref() {
+            counter.start();
+        }
+        let start = Instant::now();
+        let frame_result = black_box(execute_frame(
+            spec_id,
+            interpreter,
+            &context_builder,
+            instructions,
+        ));
+        elapsed = elapsed.min(start.elapsed());
+        if let Some(counter) = counter.as_ref() {
+            instruction_count = instruction_count.map(|count| count.min(counter.stop()));
+        }
+        result = Some(frame_result.interpreter_result().clone());
+    }
+
+    Ok(Measurement {
+        instruction_count,
+        elapsed,
+        report: None,
+        result,
+    })
+}
+
+/// Copies the inputs into a private memory buffer, as the guest sees them after deserialization,
+/// so that every sample starts from the same state.
+fn detach(
+    interpreter: &InterpreterT,
+    context_builder: &ContextBuilder,
+) -> (InterpreterT, ContextBuilder) {
+    let buffer = Rc::new(RefCell::new(
+        context_builder.shared_memory_buffer.borrow().clone(),
+    ));
+    let mut context_builder = context_builder.clone();
+    context_builder.shared_memory_buffer = buffer.clone();
+    let mut interpreter = interpreter.clone();
+    interpreter.memory = SharedMemory::new_with_buffer(buffer);
+    (interpreter, context_builder)
+}
@@ -0,0 +1,129 @@
+//! Minimal access to the Linux perf counter of retired user-space instructions.
+
+#[cfg(target_os = "linux")]
+pub(super) use linux::InstructionCounter;
+
+#[cfg(not(target_os = "linux"))]
+pub(super) use fallback::InstructionCounter;
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::mem;
+
+    const PERF_TYPE_HARDWARE: u32 = 0;
+    const PERF_COUNT_HW_INSTRUCTIONS: u64 = 1;
+
+    const FLAG_DISABLED: u64 = 1 << 0;
+    const FLAG_EXCLUDE_KERNEL: u64 = 1 << 5;
+    const FLAG_EXCLUDE_HV: u64 = 1 << 6;
+
+    const PERF_EVENT_IOC_ENABLE: u64 = 0x2400;
+    const PERF_EVENT_IOC_DISABLE: u64 = 0x2401;
+    const PERF_EVENT_IOC_RESET: u64 = 0x2403;
+
+    /// `struct perf_event_attr` as of `PERF_ATTR_SIZE_VER8`.
+    #[repr(C)]
+    #[derive(Default)]
+    struct PerfEventAttr {
+        type_: u32,
+        size: u32,
+        config: u64,
+        sample_period: u64,
+        sample_type: u64,
+        read_format: u64,
+        flags: u64,
+        wakeup_events: u32,
+        bp_type: u32,
+        config1: u64,
+        config2: u64,
+        branch_sample_type: u64,
+        sample_regs_user: u64,
+        sample_stack_user: u32,
+        clockid: i32,
+        sample_regs_intr: u64,
+        aux_watermark: u32,
+        sample_max_stack: u16,
+        reserved_2: u16,
+        aux_sample_size: u32,
+        reserved_3: u32,
+        sig_data: u64,
+        config3: u64,
+    }
+
+    /// Counts the instructions retired by the calling thread in user space.
+    pub(crate) struct InstructionCounter {
+        fd: libc::c_int,
+    }
+
+    impl InstructionCounter {
+        /// Opens a counter for the calling thread, `None` if perf events are not permitted.
+        pub(crate) fn open() -> Option<Self> {
+            let attr = PerfEventAttr {
+                type_: PERF_TYPE_HARDWARE,
+                size: mem::size_of::<PerfEventAttr>() as u32,
+                config: PERF_COUNT_HW_INSTRUCTIONS,
+                flags: FLAG_DISABLED | FLAG_EXCLUDE_KERNEL | FLAG_EXCLUDE_HV,
+                ..Default::default()
+            };
+            let fd = unsafe {
+                libc::syscall(
+                    libc::SYS_perf_event_open,
+                    &attr as *const PerfEventAttr,
+                    0 as libc::pid_t,
+                    -1 as libc::c_int,
+                    -1 as libc::c_int,
+                    0 as libc::c_ulong,
+                )
+            };
+            (fd >= 0).then_some(Self {
+                fd: fd as libc::c_int,
+            })
+        }
+
+        pub(crate) fn start(&self) {
+            unsafe {
+                libc::ioctl(self.fd, PERF_EVENT_IOC_RESET as _, 0);
+                libc::ioctl(self.fd, PERF_EVENT_IOC_ENABLE as _, 0);
+            }
+        }
+
+        /// Stops counting and returns the instructions retired since [`Self::start`].
+        pub(crate) fn stop(&self) -> u64 {
+            let mut count = 0u64;
+            unsafe {
+                libc::ioctl(self.fd, PERF_EVENT_IOC_DISABLE as _, 0);
+                libc::read(
+                    self.fd,
+                    &mut count as *mut u64 as *mut libc::c_void,
+                    mem::size_of::<u64>(),
+                );
+            }
+            count
+        }
+    }
+
+    impl Drop for InstructionCounter {
+        fn drop(&mut self) {
+            unsafe {
+                libc::close(self.fd);
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod fallback {
+    pub(crate) struct InstructionCounter;
+
+    impl InstructionCounter {
+        pub(crate) fn open() -> Option<Self> {
+            None
+        }
+
+        pub(crate) fn start(&self) {}
+
+        pub(crate) fn stop(&self) -> u64 {
+            0
+        }
+    }
+}
@@ -0,0 +1,491 @@
+//! An rv32im hart: the base integer instruction set plus the M extension.
+
+use crate::backend::rv32im::memory::Memory;
+use std::fmt::{self, Display};
+
+/// Register numbers used by the SP1 syscall ABI.
+pub(crate) const T0: usize = 5;
+pub(crate) const A0: usize = 10;
+pub(crate) const A1: usize = 11;
+pub(crate) const A2: usize = 12;
+
+#[derive(Debug)]
+pub(crate) enum Trap {
+    IllegalInstruction { pc: u32, instruction: u32 },
+    MisalignedAccess { pc: u32, addr: u32 },
+    Breakpoint { pc: u32 },
+}
+
+/// What the hart needs from its environment after executing an instruction.
+pub(crate) enum Step {
+    Continue,
+    Ecall,
+}
+
+pub(crate) struct Cpu {
+    pub(crate) regs: [u32; 32],
+    pub(crate) pc: u32,
+    pub(crate) memory: Memory,
+    /// Number of instructions executed, including `ecall`s.
+    pub(crate) instruction_count: u64,
+}
+
+impl Cpu {
+    pub(crate) fn new(pc: u32, memory: Memory) -> Self {
+        Self {
+            regs: [0; 32],
+            pc,
+            memory,
+            instruction_count: 0,
+        }
+    }
+
+    #[inline]
+    fn set(&mut self, rd: usize, value: u32) {
+        if rd != 0 {
+            self.regs[rd] = value;
+        }
+    }
+
+    pub(crate) fn step(&mut self) -> Result<Step, Trap> {
+        let pc = self.pc;
+        if pc % 4 != 0 {
+            return Err(Trap::MisalignedAccess { pc, addr: pc });
+        }
+        let instruction = self.memory.read_u32(pc);
+        let illegal = Trap::IllegalInstruction { pc, instruction };
+
+        let opcode = instruction & 0x7f;
+        let rd = ((instruction >> 7) & 0x1f) as usize;
+        let funct3 = (instruction >> 12) & 0x7;
+        let rs1 = ((instruction >> 15) & 0x1f) as usize;
+        let rs2 = ((instruction >> 20) & 0x1f) as usize;
+        let funct7 = instruction >> 25;
+
+        let imm_i = (instruction as i32) >> 20;
+        let imm_s = ((instruction as i32) >> 25 << 5) | ((instruction >> 7) & 0x1f) as i32;
+        let imm_b = ((instruction as i32) >> 31 << 12)
+            | (((instruction >> 7) & 0x1) << 11) as i32
+            | (((instruction >> 25) & 0x3f) << 5) as i32
+            | (((instruction >> 8) & 0xf) << 1) as i32;
+        let imm_u = instruction & 0xffff_f000;
+        let imm_j = ((instruction as i32) >> 31 << 20)
+            | (instruction & 0x000f_f000) as i32
+            | (((instruction >> 20) & 0x1) << 11) as i32
+            | (((instruction >> 21) & 0x3ff) << 1) as i32;
+
+        let x1 = self.regs[rs1];
+        let x2 = self.regs[rs2];
+        let mut next_pc = pc.wrapping_add(4);
+        let mut step = Step::Continue;
+
+        match opcode {
+            // LUI
+            0b0110111 => self.set(rd, imm_u),
+            // AUIPC
+            0b0010111 => self.set(rd, pc.wrapping_add(imm_u)),
+            // JAL
+            0b1101111 => {
+                self.set(rd, next_pc);
+                next_pc = pc.wrapping_add(imm_j as u32);
+            }
+            // JALR
+            0b1100111 => {
+                if funct3 != 0 {
+                    return Err(illegal);
+                }
+                let target = x1.wrapping_add(imm_i as u32) & !1;
+                self.set(rd, next_pc);
+                next_pc = target;
+            }
+            // BRANCH
+            0b1100011 => {
+                let taken = match funct3 {
+                    0b000 => x1 == x2,
+                    0b001 => x1 != x2,
+                    0b100 => (x1 as i32) < (x2 as i32),
+                    0b101 => (x1 as i32) >= (x2 as i32),
+                    0b110 => x1 < x2,
+                    0b111 => x1 >= x2,
+                    _ => return Err(illegal),
+                };
+                if taken {
+                    next_pc = pc.wrapping_add(imm_b as u32);
+                }
+            }
+            // LOAD
+            0b0000011 => {
+                let addr = x1.wrapping_add(imm_i as u32);
+                let value = match funct3 {
+                    0b000 => self.memory.read_u8(addr) as i8 as i32 as u32,
+                    0b001 => self.load_u16(pc, addr)? as i16 as i32 as u32,
+                    0b010 => self.load_u32(pc, addr)?,
+                    0b100 => self.memory.read_u8(addr) as u32,
+                    0b101 => self.load_u16(pc, addr)? as u32,
+                    _ => return Err(illegal),
+                };
+                self.set(rd, value);
+            }
+            // STORE
+            0b0100011 => {
+                let addr = x1.wrapping_add(imm_s as u32);
+                match funct3 {
+                    0b000 => self.memory.write_u8(addr, x2 as u8),
+                    0b001 => {
+                        if addr % 2 != 0 {
+                            return Err(Trap::MisalignedAccess { pc, addr });
+                        }
+                        self.memory.write_u16(addr, x2 as u16)
+                    }
+                    0b010 => {
+                        if addr % 4 != 0 {
+                            return Err(Trap::MisalignedAccess { pc, addr });
+                        }
+                        self.memory.write_u32(addr, x2)
+                    }
+                    _ => return Err(illegal),
+                }
+            }
+            // OP-IMM
+            0b0010011 => {
+                let shamt = (instruction >> 20) & 0x1f;
+                let value = match funct3 {
+                    0b000 => x1.wrapping_add(imm_i as u32),
+                    0b010 => ((x1 as i32) < imm_i) as u32,
+                    0b011 => (x1 < imm_i as u32) as u32,
+                    0b100 => x1 ^ imm_i as u32,
+                    0b110 => x1 | imm_i as u32,
+                    0b111 => x1 & imm_i as u32,
+                    0b001 if funct7 == 0 => x1 << shamt,
+                    0b101 if funct7 == 0 => x1 >> shamt,
+                    0b101 if funct7 == 0b0100000 => ((x1 as i32) >> shamt) as u32,
+                    _ => return Err(illegal),
+                };
+                self.set(rd, value);
+            }
+            // OP
+            0b0110011 => {
+                let value = match (funct7, funct3) {
+                    (0b0000000, 0b000) => x1.wrapping_add(x2),
+                    (0b0100000, 0b000) => x1.wrapping_sub(x2),
+                    (0b0000000, 0b001) => x1 << (x2 & 0x1f),
+                    (0b0000000, 0b010) => ((x1 as i32) < (x2 as i32)) as u32,
+                    (0b0000000, 0b011) => (x1 < x2) as u32,
+                    (0b0000000, 0b100) => x1 ^ x2,
+                    (0b0000000, 0b101) => x1 >> (x2 & 0x1f),
+                    (0b0100000, 0b101) => ((x1 as i32) >> (x2 & 0x1f)) as u32,
+                    (0b0000000, 0b110) => x1 | x2,
+                    (0b0000000, 0b111) => x1 & x2,
+                    (0b0000001, funct3) => mul_div(funct3, x1, x2),
+                    _ => return Err(illegal),
+                };
+                self.set(rd, value);
+            }
+            // MISC-MEM, a single hart has nothing to order
+            0b0001111 => {}
+            // SYSTEM
+            0b1110011 => match instruction {
+                0x0000_0073 => step = Step::Ecall,
+                0x0010_0073 => return Err(Trap::Breakpoint { pc }),
+                _ => return Err(illegal),
+            },
+            _ => return Err(illegal),
+        }
+
+        self.pc = next_pc;
+        self.instruction_count += 1;
+        Ok(step)
+    }
+
+    fn load_u16(&self, pc: u32, addr: u32) -> Result<u16, Trap> {
+        if addr % 2 != 0 {
+            return Err(Trap::MisalignedAccess { pc, addr });
+        }
+        Ok(self.memory.read_u16(addr))
+    }
+
+    fn load_u32(&self, pc: u32, addr: u32) -> Result<u32, Trap> {
+        if addr % 4 != 0 {
+            return Err(Trap::MisalignedAccess { pc, addr });
+        }
+        Ok(self.memory.read_u32(addr))
+    }
+}
+
+/// The M extension, following the RISC-V semantics for division by zero and overflow.
+fn mul_div(funct3: u32, x1: u32, x2: u32) -> u32 {
+    let (s1, s2) = (x1 as i32, x2 as i32);
+    match funct3 {
+        // MUL
+        0b000 => x1.wrapping_mul(x2),
+        // MULH
+        0b001 => ((s1 as i64 * s2 as i64) >> 32) as u32,
+        // MULHSU
+        0b010 => ((s1 as i64 * x2 as i64) >> 32) as u32,
+        // MULHU
+        0b011 => ((x1 as u64 * x2 as u64) >> 32) as u32,
+        // DIV
+        0b100 => match s2 {
+            0 => u32::MAX,
+            _ => s1.wrapping_div(s2) as u32,
+        },
+        // DIVU
+        0b101 => x1.checked_div(x2).unwrap_or(u32::MAX),
+        // REM
+        0b110 => match s2 {
+            0 => x1,
+            _ => s1.wrapping_rem(s2) as u32,
+        },
+        // REMU
+        0b111 => x1.checked_rem(x2).unwrap_or(x1),
+        _ => unreachable!("funct3 is 3 bits"),
+    }
+}
+
+impl Display for Trap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Trap::IllegalInstruction { pc, instruction } => {
+                write!(f, "illegal instruction {instruction:#010x} at {pc:#010x}")
+            }
+            Trap::MisalignedAccess { pc, addr } => {
+                write!(f, "misaligned access to {addr:#010x} at {pc:#010x}")
+            }
+            Trap::Breakpoint { pc } => write!(f, "breakpoint at {pc:#010x}"),
+        }
+    }
+}
+
+/// Encoders for the instructions used by the tests.
+#[cfg(test)]
+pub(crate) mod asm {
+    pub(crate) const ECALL: u32 = 0x0000_0073;
+    pub(crate) const EBREAK: u32 = 0x0010_0073;
+
+    pub(crate) fn r(funct7: u32, funct3: u32, rd: usize, rs1: usize, rs2: usize) -> u32 {
+        funct7 << 25
+            | (rs2 as u32) << 20
+            | (rs1 as u32) << 15
+            | funct3 << 12
+            | (rd as u32) << 7
+            | 0b0110011
+    }
+
+    pub(crate) fn i(opcode: u32, funct3: u32, rd: usize, rs1: usize, imm: i32) -> u32 {
+        (imm as u32 & 0xfff) << 20 | (rs1 as u32) << 15 | funct3 << 12 | (rd as u32) << 7 | opcode
+    }
+
+    pub(crate) fn s(funct3: u32, rs1: usize, rs2: usize, imm: i32) -> u32 {
+        let imm = imm as u32;
+        (imm >> 5 & 0x7f) << 25
+            | (rs2 as u32) << 20
+            | (rs1 as u32) << 15
+            | funct3 << 12
+            | (imm & 0x1f) << 7
+            | 0b0100011
+    }
+
+    pub(crate) fn b(funct3: u32, rs1: usize, rs2: usize, imm: i32) -> u32 {
+        let imm = imm as u32;
+        (imm >> 12 & 0x1) << 31
+            | (imm >> 5 & 0x3f) << 25
+            | (rs2 as u32) << 20
+            | (rs1 as u32) << 15
+            | funct3 << 12
+            | (imm >> 1 & 0xf) << 8
+            | (imm >> 11 & 0x1) << 7
+            | 0b1100011
+    }
+
+    pub(crate) fn jal(rd: usize, imm: i32) -> u32 {
+        let imm = imm as u32;
+        (imm >> 20 & 0x1) << 31
+            | (imm >> 1 & 0x3ff) << 21
+            | (imm >> 11 & 0x1) << 20
+            | (imm >> 12 & 0xff) << 12
+            | (rd as u32) << 7
+            | 0b1101111
+    }
+
+    pub(crate) fn lui(rd: usize, imm: u32) -> u32 {
+        imm & 0xffff_f000 | (rd as u32) << 7 | 0b0110111
+    }
+
+    pub(crate) fn addi(rd: usize, rs1: usize, imm: i32) -> u32 {
+        i(0b0010011, 0b000, rd, rs1, imm)
+    }
+
+    /// Loads a 32-bit constant with `lui` and `addi`.
+    pub(crate) fn li(rd: usize, value: u32) -> [u32; 2] {
+        let lo = (value << 20) as i32 >> 20;
+        let hi = value.wrapping_sub(lo as u32);
+        [lui(rd, hi), addi(rd, rd, lo)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{asm::*, *};
+
+    const BASE: u32 = 0x1000;
+
+    fn cpu(program: &[u32]) -> Cpu {
+        let mut memory = Memory::new();
+        for (i, instruction) in program.iter().enumerate() {
+            memory.write_u32(BASE + 4 * i as u32, *instruction);
+        }
+        Cpu::new(BASE, memory)
+    }
+
+    /// Runs the program until it steps past its last instruction.
+    fn run(program: &[u32]) -> Cpu {
+        let mut cpu = cpu(program);
+        while cpu.pc != BASE + 4 * program.len() as u32 {
+            assert!(matches!(cpu.step(), Ok(Step::Continue)));
+        }
+        cpu
+    }
+
+    #[test]
+    fn decode_immediates() {
+        let cpu = run(&[
+            addi(1, 0, -1),
+            addi(2, 0, 2047),
+            lui(3, 0xfffff000),
+            i(0b0010011, 0b111, 4, 1, -16),   // andi
+            i(0b0010011, 0b100, 5, 0, -2048), // xori
+        ]);
+        assert_eq!(cpu.regs[1], u32::MAX);
+        assert_eq!(cpu.regs[2], 2047);
+        assert_eq!(cpu.regs[3], 0xffff_f000);
+        assert_eq!(cpu.regs[4], 0xffff_fff0);
+        assert_eq!(cpu.regs[5], 0xffff_f800);
+        assert_eq!(cpu.instruction_count, 5);
+
+        let [hi, lo] = li(6, 0xdead_beef);
+        assert_eq!(run(&[hi, lo]).regs[6], 0xdead_beef);
+    }
+
+    #[test]
+    fn alu() {
+        let mut program = Vec::new();
+        program.extend(li(1, 0x8000_0000));
+        program.extend([
+            addi(2, 0, 3),
+            r(0, 0b000, 3, 1, 2),                           // add
+            r(0b0100000, 0b000, 4, 2, 1),                   // sub
+            r(0, 0b010, 5, 1, 2),                           // slt
+            r(0, 0b011, 6, 1, 2),                           // sltu
+            r(0, 0b101, 7, 1, 2),                           // srl
+            r(0b0100000, 0b101, 8, 1, 2),                   // sra
+            i(0b0010011, 0b101, 9, 1, 0b0100000 << 5 | 31), // srai
+            i(0b0010011, 0b001, 10, 2, 30),                 // slli
+            addi(0, 0, 1),                                  // writes to x0 are dropped
+        ]);
+        let cpu = run(&program);
+        assert_eq!(cpu.regs[3], 0x8000_0003);
+        assert_eq!(cpu.regs[4], 0x8000_0003);
+        assert_eq!(cpu.regs[5], 1);
+        assert_eq!(cpu.regs[6], 0);
+        assert_eq!(cpu.regs[7], 0x1000_0000);
+        assert_eq!(cpu.regs[8], 0xf000_0000);
+        assert_eq!(cpu.regs[9], u32::MAX);
+        assert_eq!(cpu.regs[10], 0xc000_0000);
+        assert_eq!(cpu.regs[0], 0);
+    }
+
+    #[test]
+    fn mul_div_edge_cases() {
+        let min = i32::MIN as u32;
+        let minus_one = u32::MAX;
+        assert_eq!(mul_div(0b000, minus_one, minus_one), 1);
+        assert_eq!(mul_div(0b001, minus_one, minus_one), 0);
+        assert_eq!(mul_div(0b010, minus_one, minus_one), minus_one);
+        assert_eq!(mul_div(0b011, minus_one, minus_one), 0xffff_fffe);
+        // division by zero
+        assert_eq!(mul_div(0b100, 7, 0), u32::MAX);
+        assert_eq!(mul_div(0b101, 7, 0), u32::MAX);
+        assert_eq!(mul_div(0b110, 7, 0), 7);
+        assert_eq!(mul_div(0b111, 7, 0), 7);
+        // signed overflow
+        assert_eq!(mul_div(0b100, min, minus_one), min);
+        assert_eq!(mul_div(0b110, min, minus_one), 0);
+        // rounds towards zero
+        assert_eq!(mul_div(0b100, -7i32 as u32, 2), -3i32 as u32);
+        assert_eq!(mul_div(0b110, -7i32 as u32, 2), -1i32 as u32);
+    }
+
+    #[test]
+    fn branches_and_jumps() {
+        let cpu = run(&[
+            addi(1, 0, 3),
+            // loop: x2 += 1, x1 -= 1 while x1 != 0
+            addi(2, 2, 1),
+            addi(1, 1, -1),
+            b(0b001, 1, 0, -8), // bne
+            jal(3, 8),
+            addi(4, 0, 1),                // skipped
+            i(0b1100111, 0b000, 5, 3, 8), // jalr
+        ]);
+        assert_eq!(cpu.regs[2], 3);
+        assert_eq!(cpu.regs[3], BASE + 4 * 5);
+        assert_eq!(cpu.regs[4], 0);
+        assert_eq!(cpu.regs[5], BASE + 4 * 7);
+        assert_eq!(cpu.instruction_count, 1 + 3 * 3 + 2);
+    }
+
+    #[test]
+    fn loads_and_stores() {
+        let mut program = Vec::new();
+        program.extend(li(1, 0x2000));
+        program.extend(li(2, 0x8081_82ff));
+        program.extend([
+            s(0b010, 1, 2, 4),            // sw
+            i(0b0000011, 0b000, 3, 1, 4), // lb
+            i(0b0000011, 0b100, 4, 1, 4), // lbu
+            i(0b0000011, 0b001, 5, 1, 6), // lh
+            i(0b0000011, 0b101, 6, 1, 6), // lhu
+            s(0b000, 1, 2, -1),           // sb
+            i(0b0000011, 0b010, 7, 1, 4), // lw
+        ]);
+        let cpu = run(&program);
+        assert_eq!(cpu.regs[3], 0xffff_ffff);
+        assert_eq!(cpu.regs[4], 0xff);
+        assert_eq!(cpu.regs[5], 0xffff_8081);
+        assert_eq!(cpu.regs[6], 0x8081);
+        assert_eq!(cpu.regs[7], 0x8081_82ff);
+        assert_eq!(cpu.memory.read_u8(0x1fff), 0xff);
+    }
+
+    #[test]
+    fn traps() {
+        let mut misaligned = cpu(&[addi(1, 0, 0x102), i(0b0000011, 0b010, 2, 1, 0)]);
+        misaligned.step().unwrap();
+        assert!(matches!(
+            misaligned.step(),
+            Err(Trap::MisalignedAccess { addr: 0x102, .. })
+        ));
+
+        assert!(matches!(
+            cpu(&[0xffff_ffff]).step(),
+            Err(Trap::IllegalInstruction {
+                pc: BASE,
+                instruction: 0xffff_ffff
+            })
+        ));
+        // funct7 of the shift immediates is checked
+        assert!(matches!(
+            cpu(&[i(0b0010011, 0b001, 1, 1, 0b0100000 << 5)]).step(),
+            Err(Trap::IllegalInstruction { .. })
+        ));
+        assert!(matches!(
+            cpu(&[EBREAK]).step(),
+            Err(Trap::Breakpoint { pc: BASE })
+        ));
+
+        let mut ecall = cpu(&[ECALL]);
+        assert!(matches!(ecall.step(), Ok(Step::Ecall)));
+        assert_eq!(ecall.instruction_count, 1);
+        assert_eq!(ecall.pc, BASE + 4);
+    }
+}
@@ -0,0 +1,196 @@
+//! Loader for the statically linked 32-bit RISC-V ELF files produced by `sp1-build`.
+
+use std::fmt::{self, Display};
+
+const ELF_MAGIC: [u8; 4] = [0x7f, b'E', b'L', b'F'];
+const ELFCLASS32: u8 = 1;
+const ELFDATA2LSB: u8 = 1;
+const EM_RISCV: u16 = 243;
+const PT_LOAD: u32 = 1;
+
+#[derive(Debug)]
+pub(crate) enum ElfError {
+    Truncated,
+    NotElf,
+    Unsupported(&'static str),
+    MisalignedSegment(u32),
+}
+
+/// A loadable image: the entry point and the initial contents of memory.
+#[derive(Debug, Clone)]
+pub(crate) struct Program {
+    pub(crate) entry: u32,
+    /// `(vaddr, bytes)` of each loadable segment, with the bss part already zero-filled.
+    pub(crate) segments: Vec<(u32, Vec<u8>)>,
+}
+
+impl Program {
+    pub(crate) fn load(elf: &[u8]) -> Result<Self, ElfError> {
+        if elf.len() < 52 {
+            return Err(ElfError::Truncated);
+        }
+        if elf[..4] != ELF_MAGIC {
+            return Err(ElfError::NotElf);
+        }
+        if elf[4] != ELFCLASS32 {
+            return Err(ElfError::Unsupported("only 32-bit ELF files are supported"));
+        }
+        if elf[5] != ELFDATA2LSB {
+            return Err(ElfError::Unsupported(
+                "only little-endian ELF files are supported",
+            ));
+        }
+        if read_u16(elf, 0x12)? != EM_RISCV {
+            return Err(ElfError::Unsupported("only RISC-V ELF files are supported"));
+        }
+
+        let entry = read_u32(elf, 0x18)?;
+        let ph_offset = read_u32(elf, 0x1c)? as usize;
+        let ph_entry_size = read_u16(elf, 0x2a)? as usize;
+        let ph_count = read_u16(elf, 0x2c)? as usize;
+
+        let mut segments = Vec::new();
+        for i in 0..ph_count {
+            let header = ph_offset + i * ph_entry_size;
+            if read_u32(elf, header)? != PT_LOAD {
+                continue;
+            }
+            let offset = read_u32(elf, header + 0x04)? as usize;
+            let vaddr = read_u32(elf, header + 0x08)?;
+            let file_size = read_u32(elf, header + 0x10)? as usize;
+            let mem_size = read_u32(elf, header + 0x14)? as usize;
+            if vaddr % 4 != 0 {
+                return Err(ElfError::MisalignedSegment(vaddr));
+            }
+
+            let mut bytes = elf
+                .get(offset..offset + file_size)
+                .ok_or(ElfError::Truncated)?
+                .to_vec();
+            bytes.resize(mem_size.max(file_size), 0);
+            segments.push((vaddr, bytes));
+        }
+
+        Ok(Self { entry, segments })
+    }
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Result<u16, ElfError> {
+    bytes
+        .get(offset..offset + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or(ElfError::Truncated)
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, ElfError> {
+    bytes
+        .get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or(ElfError::Truncated)
+}
+
+impl Display for ElfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ElfError::Truncated => write!(f, "truncated ELF file"),
+            ElfError::NotElf => write!(f, "not an ELF file"),
+            ElfError::Unsupported(reason) => write!(f, "{reason}"),
+            ElfError::MisalignedSegment(vaddr) => {
+                write!(f, "segment at {vaddr:#010x} is not word aligned")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PT_NOTE: u32 = 4;
+
+    /// An ELF image with a program header per `(type, vaddr, bytes, mem_size)` segment.
+    fn elf(entry: u32, segments: &[(u32, u32, &[u8], u32)]) -> Vec<u8> {
+        let ph_offset = 52;
+        let mut data_offset = ph_offset + 32 * segments.len();
+        let mut image = vec![0u8; data_offset];
+        image[..4].copy_from_slice(&ELF_MAGIC);
+        image[4] = ELFCLASS32;
+        image[5] = ELFDATA2LSB;
+        image[6] = 1;
+        image[0x12..0x14].copy_from_slice(&EM_RISCV.to_le_bytes());
+        image[0x18..0x1c].copy_from_slice(&entry.to_le_bytes());
+        image[0x1c..0x20].copy_from_slice(&(ph_offset as u32).to_le_bytes());
+        image[0x2a..0x2c].copy_from_slice(&32u16.to_le_bytes());
+        image[0x2c..0x2e].copy_from_slice(&(segments.len() as u16).to_le_bytes());
+        for (i, (p_type, vaddr, bytes, mem_size)) in segments.iter().enumerate() {
+            let header = ph_offset + 32 * i;
+            for (offset, value) in [
+                (0x00, *p_type),
+                (0x04, data_offset as u32),
+                (0x08, *vaddr),
+                (0x10, bytes.len() as u32),
+                (0x14, *mem_size),
+            ] {
+                image[header + offset..header + offset + 4].copy_from_slice(&value.to_le_bytes());
+            }
+            image.extend_from_slice(bytes);
+            data_offset += bytes.len();
+        }
+        image
+    }
+
+    #[test]
+    fn load_segments() {
+        let image = elf(
+            0x1004,
+            &[
+                (PT_LOAD, 0x1000, &[1, 2, 3, 4, 5, 6, 7, 8], 8),
+                (PT_NOTE, 0x3000, &[9; 4], 4),
+                (PT_LOAD, 0x2000, &[10, 11], 6),
+            ],
+        );
+        let program = Program::load(&image).unwrap();
+        assert_eq!(program.entry, 0x1004);
+        assert_eq!(
+            program.segments,
+            vec![
+                (0x1000, vec![1, 2, 3, 4, 5, 6, 7, 8]),
+                (0x2000, vec![10, 11, 0, 0, 0, 0]),
+            ]
+        );
+    }
+
+    #[test]
+    fn reject_invalid() {
+        let image = elf(0x1000, &[(PT_LOAD, 0x1000, &[0; 4], 4)]);
+        assert!(matches!(
+            Program::load(&image[..40]),
+            Err(ElfError::Truncated)
+        ));
+        assert!(matches!(
+            Program::load(&image[..image.len() - 1]),
+            Err(ElfError::Truncated)
+        ));
+
+        let mut not_elf = image.clone();
+        not_elf[0] = 0;
+        assert!(matches!(Program::load(&not_elf), Err(ElfError::NotElf)));
+
+        let mut elf64 = image.clone();
+        elf64[4] = 2;
+        assert!(matches!(
+            Program::load(&elf64),
+            Err(ElfError::Unsupported(_))
+        ));
+
+        let mut x86 = image.clone();
+        x86[0x12] = 3;
+        assert!(matches!(Program::load(&x86), Err(ElfError::Unsupported(_))));
+
+        let misaligned = elf(0x1000, &[(PT_LOAD, 0x1002, &[0; 4], 4)]);
+        assert!(matches!(
+            Program::load(&misaligned),
+            Err(ElfError::MisalignedSegment(0x1002))
+        ));
+    }
+}
@@ -0,0 +1,76 @@
+//! Sparse byte-addressable memory covering the whole 32-bit address space.
+
+const PAGE_BITS: u32 = 12;
+const PAGE_SIZE: usize = 1 << PAGE_BITS;
+const PAGE_MASK: u32 = PAGE_SIZE as u32 - 1;
+
+type Page = Box<[u8; PAGE_SIZE]>;
+
+pub(crate) struct Memory {
+    pages: Vec<Option<Page>>,
+}
+
+impl Memory {
+    pub(crate) fn new() -> Self {
+        Self {
+            pages: (0..1usize << (32 - PAGE_BITS)).map(|_| None).collect(),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn read_u8(&self, addr: u32) -> u8 {
+        match &self.pages[(addr >> PAGE_BITS) as usize] {
+            Some(page) => page[(addr & PAGE_MASK) as usize],
+            None => 0,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn write_u8(&mut self, addr: u32, value: u8) {
+        self.pages[(addr >> PAGE_BITS) as usize].get_or_insert_with(|| Box::new([0; PAGE_SIZE]))
+            [(addr & PAGE_MASK) as usize] = value;
+    }
+
+    /// Reads a naturally aligned half word, the caller is responsible for checking alignment.
+    #[inline]
+    pub(crate) fn read_u16(&self, addr: u32) -> u16 {
+        u16::from_le_bytes([self.read_u8(addr), self.read_u8(addr + 1)])
+    }
+
+    /// Reads a naturally aligned word, the caller is responsible for checking alignment.
+    #[inline]
+    pub(crate) fn read_u32(&self, addr: u32) -> u32 {
+        match &self.pages[(addr >> PAGE_BITS) as usize] {
+            Some(page) => {
+                let offset = (addr & PAGE_MASK) as usize;
+                u32::from_le_bytes(page[offset..offset + 4].try_into().unwrap())
+            }
+            None => 0,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn write_u16(&mut self, addr: u32, value: u16) {
+        let [b0, b1] = value.to_le_bytes();
+        self.write_u8(addr, b0);
+        self.write_u8(addr + 1, b1);
+    }
+
+    #[inline]
+    pub(crate) fn write_u32(&mut self, addr: u32, value: u32) {
+        let page = self.pages[(addr >> PAGE_BITS) as usize]
+            .get_or_insert_with(|| Box::new([0; PAGE_SIZE]));
+        let offset = (addr & PAGE_MASK) as usize;
+        page[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    pub(crate) fn read_bytes(&self, addr: u32, len: usize) -> Vec<u8> {
+        (0..len as u32).map(|i| self.read_u8(addr + i)).collect()
+    }
+
+    pub(crate) fn write_bytes(&mut self, addr: u32, bytes: &[u8]) {
+        for (i, byte) in bytes.iter().enumerate() {
+            self.write_u8(addr + i as u32, *byte);
+        }
+    }
+}
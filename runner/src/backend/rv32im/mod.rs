@@ -0,0 +1,305 @@
+//! A self-contained rv32im interpreter that runs the guest ELF with host implementations of
+//! SP1's I/O and syscall ABI, counting instructions the way SP1's executor does.
+
+use crate::{
+    GUEST_ELF,
+    backend::{Measurement, sp1},
+};
+use cpu::{A0, A1, A2, Cpu, Step, T0, Trap};
+use elf::Program;
+use evm_guest::{ContextBuilder, InterpreterT, SpecId};
+use memory::Memory;
+use sp1_sdk::SP1PublicValues;
+use std::{
+    collections::VecDeque,
+    fmt::{self, Display},
+    io::{Write, stderr, stdout},
+    sync::{
+        LazyLock,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Instant,
+};
+
+mod cpu;
+mod elf;
+mod memory;
+
+const HALT: u32 = 0x00_00_00_00;
+const WRITE: u32 = 0x00_00_00_02;
+const COMMIT: u32 = 0x00_00_00_10;
+const COMMIT_DEFERRED_PROOFS: u32 = 0x00_00_00_1a;
+const HINT_LEN: u32 = 0x00_00_00_f0;
+const HINT_READ: u32 = 0x00_00_00_f1;
+
+const FD_STDOUT: u32 = 1;
+const FD_STDERR: u32 = 2;
+const FD_PUBLIC_VALUES: u32 = 3;
+const FD_HINT: u32 = 4;
+
+static GUEST_PROGRAM: LazyLock<Program> =
+    LazyLock::new(|| Program::load(GUEST_ELF).expect("failed to load guest ELF"));
+
+/// Executions between two cross-checks with the SP1 executor, unless configured otherwise.
+pub(crate) const DEFAULT_CROSS_CHECK_INTERVAL: usize = 100;
+
+/// Every n-th execution is repeated with the SP1 executor and the counts compared, 0 disables.
+static CROSS_CHECK_INTERVAL: AtomicUsize = AtomicUsize::new(DEFAULT_CROSS_CHECK_INTERVAL);
+static EXECUTIONS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug)]
+pub(crate) enum EmulatorError {
+    Trap(Trap),
+    UnsupportedSyscall { pc: u32, syscall: u32 },
+    HintExhausted,
+    HintLengthMismatch { expected: usize, actual: usize },
+    Exit(u32),
+}
+
+/// The observable outcome of running a program to completion.
+#[derive(Debug)]
+pub(crate) struct Execution {
+    pub(crate) instruction_count: u64,
+    pub(crate) public_values: Vec<u8>,
+}
+
+pub(crate) fn set_cross_check_interval(interval: usize) {
+    CROSS_CHECK_INTERVAL.store(interval, Ordering::Relaxed);
+}
+
+pub(super) fn execute(
+    spec_id: SpecId,
+    interpreter: &InterpreterT,
+    context_builder: &ContextBuilder,
+) -> Result<Measurement, String> {
+    let stdin = sp1::stdin(spec_id, interpreter, context_builder);
+
+    let start = Instant::now();
+    let execution = run(&GUEST_PROGRAM, stdin.buffer.into()).map_err(|e| e.to_string())?;
+    let elapsed = start.elapsed();
+
+    let interval = CROSS_CHECK_INTERVAL.load(Ordering::Relaxed);
+    if interval != 0 && EXECUTIONS.fetch_add(1, Ordering::Relaxed) % interval == 0 {
+        let expected = sp1::execute(spec_id, interpreter, context_builder)?;
+        if Some(execution.instruction_count) != expected.instruction_count {
+            return Err(format!(
+                "rv32im emulator diverged from SP1 executor, {} != {:?} instructions",
+                execution.instruction_count, expected.instruction_count
+            ));
+        }
+    }
+
+    Ok(Measurement {
+        instruction_count: Some(execution.instruction_count),
+        elapsed,
+        report: None,
+        result: Some(SP1PublicValues::from(&execution.public_values).read()),
+    })
+}
+
+/// Runs the ELF image until it halts, feeding it the given hints as SP1's stdin would.
+pub(crate) fn run(
+    program: &Program,
+    mut hints: VecDeque<Vec<u8>>,
+) -> Result<Execution, EmulatorError> {
+    let mut memory = Memory::new();
+    for (vaddr, bytes) in program.segments.iter() {
+        memory.write_bytes(*vaddr, bytes);
+    }
+    let mut cpu = Cpu::new(program.entry, memory);
+    let mut public_values = Vec::new();
+
+    loop {
+        match cpu.step().map_err(EmulatorError::Trap)? {
+            Step::Continue => continue,
+            Step::Ecall => {}
+        }
+
+        let syscall = cpu.regs[T0];
+        let (a0, a1) = (cpu.regs[A0], cpu.regs[A1]);
+        match syscall {
+            HALT => {
+                if a0 != 0 {
+                    return Err(EmulatorError::Exit(a0));
+                }
+                return Ok(Execution {
+                    instruction_count: cpu.instruction_count,
+                    public_values,
+                });
+            }
+            WRITE => {
+                let bytes = cpu.memory.read_bytes(a1, cpu.regs[A2] as usize);
+                match a0 {
+                    FD_STDOUT => stdout().write_all(&bytes).unwrap(),
+                    FD_STDERR => stderr().write_all(&bytes).unwrap(),
+                    FD_PUBLIC_VALUES => public_values.extend(bytes),
+                    FD_HINT => hints.push_back(bytes),
+                    _ => {}
+                }
+            }
+            // digests of the public values and deferred proofs, only meaningful to the prover
+            COMMIT | COMMIT_DEFERRED_PROOFS => {}
+            HINT_LEN => {
+                // SP1 returns syscall results in t0
+                cpu.regs[T0] = hints.front().map_or(u32::MAX, |hint| hint.len() as u32);
+                continue;
+            }
+            HINT_READ => {
+                let hint = hints.pop_front().ok_or(EmulatorError::HintExhausted)?;
+                if hint.len() != a1 as usize {
+                    return Err(EmulatorError::HintLengthMismatch {
+                        expected: a1 as usize,
+                        actual: hint.len(),
+                    });
+                }
+                if a0 % 4 != 0 {
+                    return Err(EmulatorError::Trap(Trap::MisalignedAccess {
+                        pc: cpu.pc - 4,
+                        addr: a0,
+                    }));
+                }
+                // SP1 writes whole words, zero padding the last one
+                for (i, chunk) in hint.chunks(4).enumerate() {
+                    let mut word = [0u8; 4];
+                    word[..chunk.len()].copy_from_slice(chunk);
+                    cpu.memory
+                        .write_u32(a0 + 4 * i as u32, u32::from_le_bytes(word));
+                }
+            }
+            _ => {
+                return Err(EmulatorError::UnsupportedSyscall {
+                    pc: cpu.pc - 4,
+                    syscall,
+                });
+            }
+        }
+        // other syscalls leave their id in t0, which is already there
+    }
+}
+
+impl Display for EmulatorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EmulatorError::Trap(trap) => write!(f, "{trap}"),
+            EmulatorError::UnsupportedSyscall { pc, syscall } => {
+                write!(f, "unsupported syscall {syscall:#010x} at {pc:#010x}")
+            }
+            EmulatorError::HintExhausted => write!(f, "guest read past the end of stdin"),
+            EmulatorError::HintLengthMismatch { expected, actual } => write!(
+                f,
+                "guest read {expected} bytes of stdin but the next input has {actual} bytes"
+            ),
+            EmulatorError::Exit(code) => write!(f, "guest exited with code {code}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cpu::asm::*;
+
+    const BASE: u32 = 0x1000;
+    const BUFFER: u32 = 0x2000;
+
+    fn program(instructions: &[[u32; 2]]) -> Program {
+        let bytes = instructions
+            .iter()
+            .flatten()
+            .flat_map(|instruction| instruction.to_le_bytes())
+            .collect();
+        Program {
+            entry: BASE,
+            segments: vec![(BASE, bytes)],
+        }
+    }
+
+    /// `ecall` with a syscall id, keeping the instructions in pairs.
+    fn syscall(id: u32) -> [[u32; 2]; 2] {
+        [li(T0, id), [ECALL, addi(0, 0, 0)]]
+    }
+
+    #[test]
+    fn read_stdin_and_commit() {
+        let mut instructions = Vec::new();
+        // a1 = hint_len()
+        instructions.extend(syscall(HINT_LEN));
+        instructions.push([addi(A1, T0, 0), addi(A2, T0, 0)]);
+        // hint_read(BUFFER, a1)
+        instructions.push(li(A0, BUFFER));
+        instructions.extend(syscall(HINT_READ));
+        // write(FD_PUBLIC_VALUES, BUFFER, a2)
+        instructions.push(li(A0, FD_PUBLIC_VALUES));
+        instructions.push(li(A1, BUFFER));
+        instructions.extend(syscall(WRITE));
+        // halt(0)
+        instructions.push(li(A0, 0));
+        instructions.extend(syscall(HALT));
+
+        let execution = run(
+            &program(&instructions),
+            VecDeque::from([vec![1, 2, 3, 4, 5]]),
+        )
+        .unwrap();
+        assert_eq!(execution.public_values, vec![1, 2, 3, 4, 5]);
+        // the halting ecall is counted, the nop after it never runs
+        assert_eq!(
+            execution.instruction_count,
+            2 * instructions.len() as u64 - 1
+        );
+    }
+
+    #[test]
+    fn hint_len_of_empty_stdin() {
+        let mut instructions = Vec::new();
+        instructions.extend(syscall(HINT_LEN));
+        instructions.push([addi(A0, T0, 0), addi(0, 0, 0)]);
+        instructions.extend(syscall(HALT));
+        assert!(matches!(
+            run(&program(&instructions), VecDeque::new()),
+            Err(EmulatorError::Exit(u32::MAX))
+        ));
+    }
+
+    #[test]
+    fn syscall_errors() {
+        let exit = [li(A0, 3), syscall(HALT)[0], syscall(HALT)[1]];
+        assert!(matches!(
+            run(&program(&exit), VecDeque::new()),
+            Err(EmulatorError::Exit(3))
+        ));
+
+        let exhausted = [
+            li(A0, BUFFER),
+            li(A1, 4),
+            syscall(HINT_READ)[0],
+            syscall(HINT_READ)[1],
+        ];
+        assert!(matches!(
+            run(&program(&exhausted), VecDeque::new()),
+            Err(EmulatorError::HintExhausted)
+        ));
+        assert!(matches!(
+            run(&program(&exhausted), VecDeque::from([vec![0; 8]])),
+            Err(EmulatorError::HintLengthMismatch {
+                expected: 4,
+                actual: 8
+            })
+        ));
+
+        let unsupported = syscall(0x99);
+        assert!(matches!(
+            run(&program(&unsupported), VecDeque::new()),
+            Err(EmulatorError::UnsupportedSyscall {
+                pc: 0x1008,
+                syscall: 0x99
+            })
+        ));
+
+        let breakpoint = [[EBREAK, addi(0, 0, 0)]];
+        assert!(matches!(
+            run(&program(&breakpoint), VecDeque::new()),
+            Err(EmulatorError::Trap(Trap::Breakpoint { pc: BASE }))
+        ));
+    }
+}
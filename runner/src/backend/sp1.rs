@@ -0,0 +1,40 @@
+use crate::{GUEST_ELF, backend::Measurement};
+use evm_guest::{ContextBuilder, InterpreterT, SpecId};
+use sp1_sdk::{CpuProver, SP1Stdin};
+use std::{sync::LazyLock, time::Instant};
+
+pub(crate) static CLIENT: LazyLock<CpuProver> = LazyLock::new(CpuProver::new);
+
+pub(super) fn stdin(
+    spec_id: SpecId,
+    interpreter: &InterpreterT,
+    context_builder: &ContextBuilder,
+) -> SP1Stdin {
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&spec_id);
+    stdin.write(interpreter);
+    stdin.write(context_builder);
+    stdin
+}
+
+pub(super) fn execute(
+    spec_id: SpecId,
+    interpreter: &InterpreterT,
+    context_builder: &ContextBuilder,
+) -> Result<Measurement, String> {
+    let stdin = stdin(spec_id, interpreter, context_builder);
+
+    let start = Instant::now();
+    let (mut public_values, report) = CLIENT
+        .execute(GUEST_ELF, &stdin)
+        .run()
+        .map_err(|e| format!("SP1 executor failed: {e}"))?;
+    let elapsed = start.elapsed();
+
+    Ok(Measurement {
+        instruction_count: Some(report.total_instruction_count()),
+        elapsed,
+        report: Some(report),
+        result: Some(public_values.read()),
+    })
+}
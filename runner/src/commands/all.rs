@@ -0,0 +1,191 @@
+use crate::commands::{CommonArgs, fit::fit_results, opcodes_precompile_run_inner};
+use clap::Args;
+use itertools::Itertools;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use revm_bytecode::OpCode;
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use test_vector::{
+    CycleLut, LutRegistry, OPCODE_TEST_VECTORS, OpCodeOrPrecompile, PRECOMPILE_TEST_VECTORS,
+    Sampling, TestCaseBuilder, TestCaseKind, fitting::Aggregate,
+};
+
+#[derive(Debug, Args)]
+pub struct AllCommand {
+    /// Where the complete opcode LUT is written
+    #[clap(long, default_value = "opcode-lut.json")]
+    opcode_lut_out: PathBuf,
+    /// Where the complete precompile LUT is written
+    #[clap(long, default_value = "precompile-lut.json")]
+    precompile_lut_out: PathBuf,
+    #[clap(long, value_enum, default_value_t)]
+    aggregate: Aggregate,
+
+    #[command(flatten)]
+    common: CommonArgs,
+}
+
+type Case = (OpCodeOrPrecompile, Arc<TestCaseBuilder>);
+
+impl AllCommand {
+    /// Measures every test vector missing from the LUTs, in stages ordered so that the helper
+    /// opcodes of a mixed case are fitted before the case runs.
+    pub fn run(self) {
+        let mut opcode_lut = match &self.common.opcode_lut {
+            Some(path) => CycleLut::read(path).expect("failed to read opcode LUT"),
+            None => CycleLut::embedded_opcodes(),
+        };
+        let mut precompile_lut = match &self.common.precompile_lut {
+            Some(path) => CycleLut::read(path).expect("failed to read precompile LUT"),
+            None => CycleLut::embedded_precompiles(),
+        };
+
+        let cases = OPCODE_TEST_VECTORS
+            .iter()
+            .map(|(op, builder)| (OpCodeOrPrecompile::OpCode(*op), builder.clone()))
+            .chain(PRECOMPILE_TEST_VECTORS.iter().map(|(name, builder)| {
+                (
+                    OpCodeOrPrecompile::Precompile(name.clone()),
+                    builder.clone(),
+                )
+            }))
+            .filter(|(name, _)| {
+                self.common.no_cache
+                    || match name {
+                        OpCodeOrPrecompile::OpCode(op) => {
+                            !opcode_lut.entries.contains_key(op.as_str())
+                        }
+                        OpCodeOrPrecompile::Precompile(name) => {
+                            !precompile_lut.entries.contains_key(name.as_ref())
+                        }
+                    }
+            })
+            .collect_vec();
+
+        let measured = cases
+            .iter()
+            .filter_map(|(name, _)| match name {
+                OpCodeOrPrecompile::OpCode(op) => Some(*op),
+                OpCodeOrPrecompile::Precompile(_) => None,
+            })
+            .collect::<BTreeSet<_>>();
+        // opcodes whose LUT entries are used as is
+        let known = opcode_lut
+            .entries
+            .keys()
+            .filter_map(|name| OpCode::parse(name))
+            .filter(|op| !measured.contains(op))
+            .collect::<BTreeSet<_>>();
+
+        let stages = schedule(dependencies(cases, self.common.seed), known);
+
+        for (idx, stage) in stages.into_iter().enumerate() {
+            eprintln!(
+                "Stage {idx}: {}",
+                stage.iter().map(|(name, _)| name.as_str()).join(", ")
+            );
+            let precompiles = stage
+                .iter()
+                .filter_map(|(name, _)| match name {
+                    OpCodeOrPrecompile::Precompile(name) => Some(name.to_string()),
+                    OpCodeOrPrecompile::OpCode(_) => None,
+                })
+                .collect::<BTreeSet<_>>();
+
+            let luts = LutRegistry::from_luts(&opcode_lut, &precompile_lut)
+                .unwrap_or_else(|e| panic!("failed to load cycle LUTs: {e}"));
+            let mut common = self.common.clone();
+            common.out = stage_path(&self.common.out, idx);
+            opcodes_precompile_run_inner(common.clone(), &luts, stage.into_iter());
+
+            for (name, entry) in fit_results(&common.out, self.aggregate) {
+                if precompiles.contains(&name) {
+                    precompile_lut.entries.insert(name, entry);
+                } else {
+                    opcode_lut.entries.insert(name, entry);
+                }
+            }
+            // written after every stage, so an interrupted run keeps what it has fitted
+            write_lut(&self.opcode_lut_out, &opcode_lut);
+            write_lut(&self.precompile_lut_out, &precompile_lut);
+        }
+    }
+}
+
+/// Parameter pairs of a mixed case counted to find its dependencies, spread over its ranges.
+const DEPENDENCY_SAMPLES: usize = 4;
+
+/// Finds the opcodes whose estimated cost a mixed case subtracts, by counting the opcodes of a
+/// few of its parameters, the helper opcodes of a case do not change with its parameters.
+fn dependencies(cases: Vec<Case>, seed: u64) -> Vec<(Case, BTreeSet<OpCode>)> {
+    cases
+        .par_iter()
+        .map(|(name, builder)| {
+            let deps = match builder.kind() {
+                TestCaseKind::ConstantSimple | TestCaseKind::DynamicSimple => BTreeSet::new(),
+                TestCaseKind::ConstantMixed | TestCaseKind::DynamicMixed => builder
+                    .sample_params(Sampling::Stratified, DEPENDENCY_SAMPLES, seed)
+                    .into_iter()
+                    .flat_map(|(repetition, input_size)| {
+                        builder
+                            .build(repetition, input_size, Some(seed))
+                            .count_opcodes()
+                            .iter()
+                            .map(|(op, _)| op)
+                            .collect_vec()
+                    })
+                    .filter(|op| !name.matches(op) && *op != OpCode::STOP)
+                    .collect(),
+            };
+            ((name.clone(), builder.clone()), deps)
+        })
+        .collect()
+}
+
+/// Groups the cases into stages, each only depending on opcodes known up front or measured by an
+/// earlier stage.
+fn schedule(
+    mut pending: Vec<(Case, BTreeSet<OpCode>)>,
+    mut known: BTreeSet<OpCode>,
+) -> Vec<Vec<Case>> {
+    let mut stages = vec![];
+    while !pending.is_empty() {
+        let (ready, rest): (Vec<_>, Vec<_>) = pending
+            .into_iter()
+            .partition(|(_, deps)| deps.is_subset(&known));
+        if ready.is_empty() {
+            for ((name, _), deps) in rest {
+                eprintln!(
+                    "Skipping {}, its dependencies can not be measured: {}",
+                    name.as_str(),
+                    deps.difference(&known).map(|op| op.as_str()).join(", ")
+                );
+            }
+            break;
+        }
+        known.extend(ready.iter().filter_map(|((name, _), _)| match name {
+            OpCodeOrPrecompile::OpCode(op) => Some(*op),
+            OpCodeOrPrecompile::Precompile(_) => None,
+        }));
+        stages.push(ready.into_iter().map(|(case, _)| case).collect());
+        pending = rest;
+    }
+    stages
+}
+
+fn stage_path(out: &Path, idx: usize) -> PathBuf {
+    let mut path = out.with_extension(format!("stage-{idx}"));
+    if let Some(extension) = out.extension() {
+        path.as_mut_os_string().push(".");
+        path.as_mut_os_string().push(extension);
+    }
+    path
+}
+
+fn write_lut(path: &Path, lut: &CycleLut) {
+    fs::write(path, serde_json::to_string_pretty(lut).unwrap()).expect("failed to write LUT");
+}
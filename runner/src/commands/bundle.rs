@@ -0,0 +1,173 @@
+use crate::{
+    backend::Backend,
+    commands::{manifest::crate_versions, replay::find_case, validate::fixture_paths},
+};
+use clap::{Args, Subcommand};
+use evm_guest::{ContextBuilder, InterpreterT, SharedMemory, SpecId};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+use test_vector::TestCase;
+
+#[derive(Debug, Args)]
+pub struct BundleCommand {
+    #[command(subcommand)]
+    command: BundleSubcommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum BundleSubcommand {
+    /// Writes the guest input of a case to a bundle
+    Dump(DumpArgs),
+    /// Executes the guest on bundles, files or directories of them
+    Run(RunArgs),
+}
+
+#[derive(Debug, Args)]
+struct DumpArgs {
+    /// Opcode or precompile of the case
+    name: String,
+    #[clap(long)]
+    seed: u64,
+    #[clap(long)]
+    repetition: usize,
+    #[clap(long)]
+    input_size: usize,
+    /// Defaults to `<name>-<seed>-<repetition>-<input size>.json`
+    #[clap(long)]
+    out: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+struct RunArgs {
+    #[clap(required = true)]
+    bundles: Vec<PathBuf>,
+    #[clap(long, value_enum, default_value_t)]
+    backend: Backend,
+}
+
+/// The exact input of the guest, `SpecId`, `InterpreterT` and `ContextBuilder` in the order the
+/// guest reads them, along with what produced it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StdinBundle {
+    /// The case the input was built from, if it was built from one.
+    #[serde(default)]
+    pub case: Option<BundleCase>,
+    pub git_revision: String,
+    pub crate_versions: BTreeMap<String, String>,
+    pub spec_id: SpecId,
+    pub interpreter: InterpreterT,
+    pub context_builder: ContextBuilder,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleCase {
+    pub name: String,
+    pub seed: u64,
+    pub repetition: usize,
+    pub input_size: usize,
+}
+
+impl BundleCommand {
+    pub fn run(self) {
+        match self.command {
+            BundleSubcommand::Dump(args) => args.run(),
+            BundleSubcommand::Run(args) => args.run(),
+        }
+    }
+}
+
+impl DumpArgs {
+    fn run(self) {
+        let (_, builder) =
+            find_case(&self.name).unwrap_or_else(|| panic!("no test vector named {}", self.name));
+        let tc = builder.build(self.repetition, self.input_size, Some(self.seed));
+        let out = self.out.clone().unwrap_or_else(|| {
+            PathBuf::from(format!(
+                "{}-{}-{}-{}.json",
+                self.name, self.seed, self.repetition, self.input_size
+            ))
+        });
+        StdinBundle::from_case(&self.name, &tc).write(&out);
+        eprintln!("Guest input written to {}.", out.display());
+    }
+}
+
+impl RunArgs {
+    fn run(self) {
+        let crate_versions = crate_versions();
+        for path in self.bundles.iter().flat_map(|path| fixture_paths(path)) {
+            let bundle = StdinBundle::read(&path);
+            if bundle.crate_versions != crate_versions {
+                eprintln!(
+                    "{} was dumped with different crate versions, from {}.",
+                    path.display(),
+                    bundle.git_revision
+                );
+            }
+            let (spec_id, interpreter, context_builder) = bundle.into_input();
+            match self
+                .backend
+                .execute(spec_id, &interpreter, &context_builder)
+            {
+                Ok(measurement) => {
+                    let result = measurement.result.as_ref().map_or_else(
+                        || "no result".to_string(),
+                        |result| format!("{:?}, {} gas used", result.result, result.gas.spent()),
+                    );
+                    let instructions = measurement
+                        .instruction_count
+                        .map_or_else(|| "uncounted".to_string(), |count| count.to_string());
+                    println!(
+                        "{}: {instructions} instructions in {:?}, {result}",
+                        path.display(),
+                        measurement.elapsed
+                    );
+                }
+                Err(e) => eprintln!("{}: {e}", path.display()),
+            }
+        }
+    }
+}
+
+impl StdinBundle {
+    pub fn from_case(name: &str, tc: &TestCase) -> Self {
+        Self {
+            case: tc.seed().map(|seed| BundleCase {
+                name: name.to_string(),
+                seed,
+                repetition: tc.repetition(),
+                input_size: tc.input_size(),
+            }),
+            git_revision: env!("GIT_REVISION").to_string(),
+            crate_versions: crate_versions(),
+            spec_id: tc.spec_id(),
+            interpreter: tc.interpreter().clone(),
+            context_builder: tc.context_builder().clone(),
+        }
+    }
+
+    pub fn read(path: &Path) -> Self {
+        let json = fs::read_to_string(path)
+            .unwrap_or_else(|e| panic!("failed to read bundle {}: {e}", path.display()));
+        serde_json::from_str(&json)
+            .unwrap_or_else(|e| panic!("failed to parse bundle {}: {e}", path.display()))
+    }
+
+    pub fn write(&self, path: &Path) {
+        fs::write(path, serde_json::to_vec(self).unwrap())
+            .unwrap_or_else(|e| panic!("failed to write bundle {}: {e}", path.display()));
+    }
+
+    /// The guest input, with the interpreter memory backed by the context buffer as the guest
+    /// rebuilds it after deserialization.
+    pub fn into_input(self) -> (SpecId, InterpreterT, ContextBuilder) {
+        let mut interpreter = self.interpreter;
+        interpreter.memory =
+            SharedMemory::new_with_buffer(self.context_builder.shared_memory_buffer.clone());
+        (self.spec_id, interpreter, self.context_builder)
+    }
+}
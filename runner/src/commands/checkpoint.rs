@@ -0,0 +1,113 @@
+use std::{
+    fmt::{self, Display},
+    fs::{File, OpenOptions},
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+use test_vector::OpCodeOrPrecompile;
+
+/// Identifies a single measurement of a sweep, stable across runs with the same arguments.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct CaseKey {
+    pub name: OpCodeOrPrecompile,
+    pub seed: u64,
+    pub repetition: usize,
+    pub input_size: usize,
+}
+
+const DONE: u8 = 1;
+const CONVERGED: u8 = 2;
+
+/// Records which cases of a sweep have been written to its output, or skipped once converged.
+pub struct Checkpoints {
+    tree: sled::Db,
+}
+
+impl Checkpoints {
+    /// Opens the checkpoints next to `out`, forgetting previous progress unless resuming.
+    pub fn open(out: &Path, resume: bool) -> Self {
+        let tree = sled::open(checkpoints_path(out)).expect("failed to open checkpoints");
+        if !resume {
+            tree.clear().expect("failed to clear checkpoints");
+        }
+        Self { tree }
+    }
+
+    pub fn is_done(&self, key: &CaseKey) -> bool {
+        self.tree
+            .contains_key(key.to_string())
+            .expect("failed to read checkpoints")
+    }
+
+    pub fn mark_done(&self, key: &CaseKey) {
+        self.tree
+            .insert(key.to_string(), &[DONE])
+            .expect("failed to mark case as done");
+    }
+
+    /// Marks a case skipped since the model of its name converged, done as far as resuming is
+    /// concerned.
+    pub fn mark_converged(&self, key: &CaseKey) {
+        self.tree
+            .insert(key.to_string(), &[CONVERGED])
+            .expect("failed to mark case as converged");
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    pub fn flush(&self) {
+        self.tree.flush().expect("failed to flush checkpoints");
+    }
+}
+
+fn checkpoints_path(out: &Path) -> PathBuf {
+    let mut path = out.as_os_str().to_owned();
+    path.push(".checkpoints");
+    PathBuf::from(path)
+}
+
+/// Opens the csv output, appending to it when resuming a previous run.
+pub fn open_csv_writer(out: &Path, resume: bool) -> csv::Writer<File> {
+    if resume && out.exists() {
+        let file = truncate_partial_row(out);
+        let has_headers = file.metadata().unwrap().len() == 0;
+        csv::WriterBuilder::new()
+            .has_headers(has_headers)
+            .from_writer(file)
+    } else {
+        csv::Writer::from_path(out).unwrap()
+    }
+}
+
+/// Drops a trailing row cut off by an interrupted run, returns the file opened for appending.
+fn truncate_partial_row(path: &Path) -> File {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .unwrap();
+    let mut content = Vec::new();
+    file.read_to_end(&mut content).unwrap();
+    let complete = content
+        .iter()
+        .rposition(|b| *b == b'\n')
+        .map_or(0, |pos| pos + 1);
+    file.set_len(complete as u64).unwrap();
+    file.seek(SeekFrom::End(0)).unwrap();
+    file
+}
+
+impl Display for CaseKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}/{}/{}/{}",
+            self.name.as_str(),
+            self.seed,
+            self.repetition,
+            self.input_size
+        )
+    }
+}
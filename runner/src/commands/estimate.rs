@@ -0,0 +1,117 @@
+use clap::Args;
+use itertools::Itertools;
+use serde::Serialize;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use test_vector::{LutRegistry, estimate::TransactionFixture};
+
+#[derive(Debug, Args)]
+pub struct EstimateCommand {
+    /// Transaction to estimate, a json with the spec, block, tx and pre-state
+    fixture: PathBuf,
+    /// Where the per opcode and precompile breakdown is written as json
+    #[clap(long)]
+    out: Option<PathBuf>,
+    /// Opcode cycle LUT used instead of the embedded one
+    #[clap(long, env = "OPCODE_CYCLE_LUT")]
+    opcode_lut: Option<PathBuf>,
+    /// Precompile cycle LUT used instead of the embedded one
+    #[clap(long, env = "PRECOMPILE_CYCLE_LUT")]
+    precompile_lut: Option<PathBuf>,
+}
+
+#[derive(Serialize)]
+struct Breakdown<'a> {
+    name: &'a str,
+    count: usize,
+    cycles: f64,
+}
+
+#[derive(Serialize)]
+struct EstimateReport<'a> {
+    total: f64,
+    opcodes: Vec<Breakdown<'a>>,
+    precompiles: Vec<Breakdown<'a>>,
+    missing: Vec<&'a str>,
+}
+
+impl EstimateCommand {
+    /// Executes the transaction with the counting instruction table and estimates its cycles.
+    pub fn run(self) {
+        let luts = LutRegistry::load(self.opcode_lut.as_deref(), self.precompile_lut.as_deref())
+            .unwrap_or_else(|e| panic!("failed to load cycle LUTs: {e}"));
+        let fixture = read_fixture(&self.fixture);
+
+        let (result, trace) = fixture.trace();
+        match result {
+            Ok(result) => eprintln!(
+                "Transaction {}, {} gas used.",
+                if result.is_success() {
+                    "succeeded"
+                } else {
+                    "failed"
+                },
+                result.gas_used()
+            ),
+            Err(e) => eprintln!("Transaction is invalid: {e:?}"),
+        }
+
+        let estimate = luts.estimate(&trace);
+        let opcodes = estimate
+            .opcodes
+            .iter()
+            .map(|(op, estimate)| Breakdown {
+                name: op.as_str(),
+                count: estimate.count,
+                cycles: estimate.cycles,
+            })
+            .sorted_by(|a, b| b.cycles.total_cmp(&a.cycles))
+            .collect_vec();
+        let precompiles = estimate
+            .precompiles
+            .iter()
+            .map(|(name, estimate)| Breakdown {
+                name,
+                count: estimate.count,
+                cycles: estimate.cycles,
+            })
+            .sorted_by(|a, b| b.cycles.total_cmp(&a.cycles))
+            .collect_vec();
+        for breakdown in precompiles.iter().chain(&opcodes) {
+            println!(
+                "{:<16} {:>10} {:>16.0} {:>6.2}%",
+                breakdown.name,
+                breakdown.count,
+                breakdown.cycles,
+                breakdown.cycles / estimate.total * 100.0
+            );
+        }
+        println!("Estimated {:.0} cycles.", estimate.total);
+        if !estimate.missing.is_empty() {
+            eprintln!(
+                "No cycle model for {}, not included in the estimate.",
+                estimate.missing.iter().map(|op| op.as_str()).join(", ")
+            );
+        }
+
+        if let Some(out) = &self.out {
+            let report = EstimateReport {
+                total: estimate.total,
+                opcodes,
+                precompiles,
+                missing: estimate.missing.iter().map(|op| op.as_str()).collect(),
+            };
+            fs::write(out, serde_json::to_string_pretty(&report).unwrap())
+                .expect("failed to write estimate");
+        }
+    }
+}
+
+pub(super) fn read_fixture(path: &Path) -> TransactionFixture {
+    let json = fs::read_to_string(path)
+        .unwrap_or_else(|e| panic!("failed to read fixture {}: {e}", path.display()));
+    serde_json::from_str(&json)
+        .unwrap_or_else(|e| panic!("failed to parse fixture {}: {e}", path.display()))
+}
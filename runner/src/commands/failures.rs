@@ -0,0 +1,165 @@
+use super::{checkpoint::CaseKey, runner::CaseError};
+use serde::Serialize;
+use std::{
+    any::Any,
+    collections::BTreeMap,
+    fs::{File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// A case of a sweep that could not be measured.
+#[derive(Debug, Clone)]
+pub struct CaseFailure {
+    pub key: CaseKey,
+    pub error: CaseError,
+}
+
+/// A line of the failures or mismatches file.
+#[derive(Serialize)]
+struct FailureRecord<'a> {
+    name: &'a str,
+    seed: u64,
+    repetition: usize,
+    input_size: usize,
+    kind: &'static str,
+    message: String,
+}
+
+/// The failures of a sweep, written as json lines next to its output. Guest results that
+/// mismatch the host go to a mismatches file of their own, everything else to the failures file.
+pub struct FailureLog {
+    failures: (PathBuf, File),
+    mismatches: (PathBuf, File),
+    /// (name, kind) -> failures
+    counts: BTreeMap<(String, &'static str), usize>,
+}
+
+impl FailureLog {
+    /// Opens the failures and mismatches files of `out`, appending to them when resuming a
+    /// previous run.
+    pub fn open(out: &Path, resume: bool) -> Self {
+        Self {
+            failures: open_log(log_path(out, "failures"), resume),
+            mismatches: open_log(log_path(out, "mismatches"), resume),
+            counts: BTreeMap::new(),
+        }
+    }
+
+    pub fn write(&mut self, failure: &CaseFailure) {
+        let record = FailureRecord {
+            name: failure.key.name.as_str(),
+            seed: failure.key.seed,
+            repetition: failure.key.repetition,
+            input_size: failure.key.input_size,
+            kind: failure.error.kind(),
+            message: failure.error.to_string(),
+        };
+        let (_, file) = match failure.error {
+            CaseError::Mismatch(_) => &mut self.mismatches,
+            _ => &mut self.failures,
+        };
+        serde_json::to_writer(&mut *file, &record).unwrap();
+        writeln!(file).expect("failed to write failures file");
+        *self
+            .counts
+            .entry((record.name.to_string(), record.kind))
+            .or_default() += 1;
+    }
+
+    /// Prints the number of failures of this run by case name and kind of failure.
+    pub fn print_summary(&self, measured: usize) {
+        let failed = self.counts.values().sum::<usize>();
+        if failed == 0 {
+            eprintln!("{measured} cases measured, none failed.");
+            return;
+        }
+        eprintln!(
+            "{measured} cases measured, {failed} failed and were not recorded, see {} and {}:",
+            self.failures.0.display(),
+            self.mismatches.0.display()
+        );
+        for ((name, kind), count) in &self.counts {
+            eprintln!("  {name:<24} {kind:<14} {count:>6}");
+        }
+    }
+}
+
+fn open_log(path: PathBuf, resume: bool) -> (PathBuf, File) {
+    let file = OpenOptions::new()
+        .create(true)
+        .append(resume)
+        .write(true)
+        .truncate(!resume)
+        .open(&path)
+        .unwrap_or_else(|e| panic!("failed to open {}: {e}", path.display()));
+    (path, file)
+}
+
+fn log_path(out: &Path, log: &str) -> PathBuf {
+    let mut path = out.as_os_str().to_owned();
+    path.push(format!(".{log}.jsonl"));
+    PathBuf::from(path)
+}
+
+/// The message of a caught panic.
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::runner::ResultMismatch;
+    use evm_guest::Bytes;
+    use revm_bytecode::OpCode;
+    use revm_interpreter::{Gas, InstructionResult, InterpreterResult};
+    use std::fs;
+    use test_vector::OpCodeOrPrecompile;
+
+    #[test]
+    fn mismatches_have_their_own_log() {
+        let out = std::env::temp_dir().join(format!("runner-mismatch-{}.csv", std::process::id()));
+        let key = CaseKey {
+            name: OpCodeOrPrecompile::OpCode(OpCode::ADD),
+            seed: 1,
+            repetition: 2,
+            input_size: 0,
+        };
+        let mut log = FailureLog::open(&out, false);
+        log.write(&CaseFailure {
+            key: key.clone(),
+            error: CaseError::Mismatch(ResultMismatch {
+                guest: InterpreterResult::new(InstructionResult::Stop, Bytes::new(), Gas::new(0)),
+                host: InterpreterResult::new(InstructionResult::Revert, Bytes::new(), Gas::new(0)),
+            }),
+        });
+        log.write(&CaseFailure {
+            key,
+            error: CaseError::Execution("guest ran out of memory".to_string()),
+        });
+        drop(log);
+
+        let read_log = |log| {
+            let path = log_path(&out, log);
+            let content = fs::read_to_string(&path).unwrap();
+            fs::remove_file(path).unwrap();
+            content
+                .lines()
+                .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+                .collect::<Vec<_>>()
+        };
+        let mismatches = read_log("mismatches");
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0]["name"], "ADD");
+        assert_eq!(mismatches[0]["repetition"], 2);
+        assert_eq!(mismatches[0]["kind"], "mismatch");
+        let failures = read_log("failures");
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0]["kind"], "execution");
+    }
+}
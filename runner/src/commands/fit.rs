@@ -0,0 +1,263 @@
+use crate::commands::{manifest::Manifest, results::read_results};
+use clap::Args;
+use std::{
+    collections::BTreeMap,
+    fs,
+    panic::catch_unwind,
+    path::{Path, PathBuf},
+};
+use test_vector::{
+    CycleLut, CycleModel, FitStats, LutEntry,
+    fitting::{Aggregate, clip_quantiles, confidence_interval, linear_fit, median, stddev_around},
+};
+
+#[derive(Debug, Args)]
+pub struct FitCommand {
+    /// Results to fit, a csv or a results database
+    #[clap(default_value = "results.csv")]
+    input: PathBuf,
+    #[clap(long, default_value = "lut.json")]
+    out: PathBuf,
+    #[clap(long, value_enum, default_value_t)]
+    aggregate: Aggregate,
+    /// Existing LUT the fitted models are merged into, replacing entries of the same name
+    #[clap(long)]
+    base: Option<PathBuf>,
+}
+
+impl FitCommand {
+    pub fn run(self) {
+        let mut lut = match &self.base {
+            Some(base) => CycleLut::read(base).expect("failed to parse base LUT"),
+            None => CycleLut::new(),
+        };
+        lut.entries.extend(fit_results(&self.input, self.aggregate));
+
+        fs::write(&self.out, serde_json::to_string_pretty(&lut).unwrap())
+            .expect("failed to write LUT");
+    }
+}
+
+/// Fits a model to each name found in the results, stamped with the manifest of the results.
+pub(super) fn fit_results(input: &Path, aggregate: Aggregate) -> BTreeMap<String, LutEntry> {
+    // name -> input size -> instruction delta per op
+    let mut samples: BTreeMap<String, BTreeMap<usize, Vec<f64>>> = BTreeMap::new();
+    let mut instructions_per_gas: BTreeMap<String, Vec<f64>> = BTreeMap::new();
+    let mut uncounted = 0;
+    for record in read_results(input) {
+        let Some(delta_per_op) = record.instruction_delta_per_op() else {
+            uncounted += 1;
+            continue;
+        };
+        if let Some(per_gas) = record.instructions_per_gas() {
+            instructions_per_gas
+                .entry(record.name.clone())
+                .or_default()
+                .push(per_gas);
+        }
+        samples
+            .entry(record.name.clone())
+            .or_default()
+            .entry(record.input_size.unwrap_or_default())
+            .or_default()
+            .push(delta_per_op);
+    }
+    if uncounted > 0 {
+        eprintln!(
+            "Skipped {uncounted} results without instruction counts, measured without perf counters."
+        );
+    }
+
+    let source_run = Manifest::read_id(input);
+    samples
+        .into_iter()
+        .map(|(name, by_input_size)| {
+            let (model, stats) = fit_model(&by_input_size, aggregate);
+            match instructions_per_gas.get(&name) {
+                Some(per_gas) => println!(
+                    "{name}: {model:?}, {stats:?}, median {:.2} instructions per gas",
+                    median(per_gas)
+                ),
+                None => println!("{name}: {model:?}, {stats:?}"),
+            }
+            let entry = LutEntry {
+                model,
+                stats: Some(stats),
+                source_run: source_run.clone(),
+            };
+            (name, entry)
+        })
+        .collect()
+}
+
+/// The samples of a case during a sweep, deciding when its fitted model is known well enough to
+/// stop sampling it.
+pub(super) struct Convergence {
+    samples: BTreeMap<usize, Vec<f64>>,
+    len: usize,
+    /// Distinct input sizes needed before fitting, a line through two points always fits.
+    min_input_sizes: usize,
+    converged: bool,
+}
+
+impl Convergence {
+    pub(super) fn new(supported_input_sizes: usize) -> Self {
+        Self {
+            samples: BTreeMap::new(),
+            len: 0,
+            min_input_sizes: supported_input_sizes.min(3),
+            converged: false,
+        }
+    }
+
+    /// Adds an instruction delta per op, the model is only refitted by `update`.
+    pub(super) fn add(&mut self, input_size: usize, value: f64) {
+        self.samples.entry(input_size).or_default().push(value);
+        self.len += 1;
+    }
+
+    /// Refits once there are enough samples. Converged once the confidence interval of the
+    /// cycles, or of the slope for a line, is narrower than `tolerance` relative to its center. A
+    /// fit that fails, or that falls back to a constant for a case measured over several input
+    /// sizes, is not converged.
+    pub(super) fn update(&mut self, tolerance: f64, min_samples: usize) {
+        if self.converged || self.len < min_samples || self.samples.len() < self.min_input_sizes {
+            return;
+        }
+        // added in the order they were measured in, the fit must not depend on it
+        for values in self.samples.values_mut() {
+            values.sort_by(f64::total_cmp);
+        }
+        let Ok((model, stats)) = catch_unwind(|| fit_model(&self.samples, Aggregate::default()))
+        else {
+            return;
+        };
+        if self.min_input_sizes > 1 && matches!(model, CycleModel::Constant { .. }) {
+            return;
+        }
+        let (lo, hi) = stats.ci;
+        let center = ((lo + hi) / 2.0).abs();
+        self.converged =
+            lo.is_finite() && hi.is_finite() && center > 0.0 && (hi - lo) / center <= tolerance;
+    }
+
+    pub(super) fn is_converged(&self) -> bool {
+        self.converged
+    }
+
+    pub(super) fn len(&self) -> usize {
+        self.len
+    }
+}
+
+/// Fits a constant model to a single input size, otherwise clips the samples to p5-p95 and fits
+/// a line through the aggregate of each input size. Falls back to a constant model when clipping
+/// leaves fewer than two input sizes.
+fn fit_model(
+    by_input_size: &BTreeMap<usize, Vec<f64>>,
+    aggregate: Aggregate,
+) -> (CycleModel, FitStats) {
+    if by_input_size.len() == 1 {
+        return fit_constant(by_input_size.values().next().unwrap(), aggregate);
+    }
+
+    let clipped = clip_quantiles(by_input_size, 0.05, 0.95);
+    if clipped.len() < 2 {
+        let values = clipped.into_values().flatten().collect::<Vec<_>>();
+        if values.is_empty() {
+            let all = by_input_size
+                .values()
+                .flatten()
+                .copied()
+                .collect::<Vec<_>>();
+            return fit_constant(&all, aggregate);
+        }
+        return fit_constant(&values, aggregate);
+    }
+
+    let (xs, ys): (Vec<f64>, Vec<f64>) = clipped
+        .iter()
+        .map(|(input_size, values)| (*input_size as f64, aggregate.apply(values)))
+        .unzip();
+    let fit = linear_fit(&xs, &ys);
+    let residuals = clipped
+        .iter()
+        .flat_map(|(input_size, values)| {
+            let x = *input_size as f64;
+            values
+                .iter()
+                .map(move |y| y - (fit.slope * x + fit.intercept))
+        })
+        .collect::<Vec<_>>();
+    let stats = FitStats {
+        n: residuals.len(),
+        r2: Some(fit.r2),
+        stddev: stddev_around(&residuals, 0.0),
+        ci: confidence_interval(fit.slope, fit.slope_stderr),
+    };
+    let model = CycleModel::Linear {
+        slope: fit.slope,
+        intercept: fit.intercept,
+    };
+    (model, stats)
+}
+
+fn fit_constant(values: &[f64], aggregate: Aggregate) -> (CycleModel, FitStats) {
+    let cycles = aggregate.apply(values);
+    let stddev = stddev_around(values, cycles);
+    let stats = FitStats {
+        n: values.len(),
+        r2: None,
+        stddev,
+        ci: confidence_interval(cycles, stddev / (values.len() as f64).sqrt()),
+    };
+    (CycleModel::Constant { cycles }, stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fit_model_falls_back_to_constant() {
+        // clipping to p5-p95 drops both samples of a two point case
+        let by_input_size = BTreeMap::from([(1, vec![10.0]), (2, vec![20.0])]);
+        let (model, stats) = fit_model(&by_input_size, Aggregate::Median);
+        assert!(matches!(model, CycleModel::Constant { cycles } if cycles == 15.0));
+        assert_eq!(stats.n, 2);
+
+        // a skewed input size is clipped away, leaving a single one
+        let by_input_size = BTreeMap::from([(1, vec![100.0; 40]), (64, vec![1e6])]);
+        let (model, _) = fit_model(&by_input_size, Aggregate::Median);
+        assert!(matches!(model, CycleModel::Constant { cycles } if cycles == 100.0));
+    }
+
+    #[test]
+    fn degenerate_fit_is_not_converged() {
+        let mut convergence = Convergence::new(2);
+        convergence.add(1, 10.0);
+        convergence.add(2, 20.0);
+        convergence.update(1.0, 1);
+        assert!(!convergence.is_converged());
+        assert_eq!(convergence.len(), 2);
+    }
+
+    #[test]
+    fn convergence_is_decided_on_update() {
+        let values = (0..20)
+            .map(|i| 100.0 + f64::from(i % 5))
+            .collect::<Vec<_>>();
+        let mut forward = Convergence::new(1);
+        let mut backward = Convergence::new(1);
+        for (a, b) in values.iter().zip(values.iter().rev()) {
+            forward.add(0, *a);
+            backward.add(0, *b);
+        }
+        assert!(!forward.is_converged());
+        forward.update(0.05, 16);
+        backward.update(0.05, 16);
+        assert!(forward.is_converged());
+        assert_eq!(forward.samples, backward.samples);
+        assert!(backward.is_converged());
+    }
+}
@@ -0,0 +1,205 @@
+use clap::Args;
+use evm_guest::SpecId;
+use itertools::Itertools;
+use revm_bytecode::OpCode;
+use serde::Serialize;
+use std::{collections::BTreeSet, fmt::Write, fs, path::PathBuf, sync::Arc};
+use test_vector::{
+    CycleParams, LutRegistry, OPCODE_TEST_VECTORS, PRECOMPILE_TEST_VECTORS, TestCaseBuilder,
+    gas::{Access, GasCost, opcode_gas, precompile_gas},
+};
+
+#[derive(Debug, Args)]
+pub struct GasReportCommand {
+    /// Specs the operations are priced at, defaults to the spec of the test vectors
+    #[clap(long, value_delimiter = ',', value_parser = parse_spec)]
+    spec: Vec<SpecId>,
+    /// Opcode the cycles per gas of every operation are normalised against
+    #[clap(long, default_value = "ADD", value_parser = parse_opcode)]
+    reference: OpCode,
+    #[clap(long, value_enum, default_value_t)]
+    access: Access,
+    /// Input size dynamic operations are priced at, defaults to the median size of their test
+    /// vector
+    #[clap(long)]
+    input_size: Option<usize>,
+    /// Number of under- and over-priced operations listed in the markdown report
+    #[clap(long, default_value_t = 10)]
+    top: usize,
+    #[clap(long, default_value = "gas-report.csv")]
+    csv: PathBuf,
+    #[clap(long, default_value = "gas-report.md")]
+    markdown: PathBuf,
+    /// Opcode cycle LUT used instead of the embedded one
+    #[clap(long, env = "OPCODE_CYCLE_LUT")]
+    opcode_lut: Option<PathBuf>,
+    /// Precompile cycle LUT used instead of the embedded one
+    #[clap(long, env = "PRECOMPILE_CYCLE_LUT")]
+    precompile_lut: Option<PathBuf>,
+}
+
+#[derive(Debug, Serialize)]
+struct ReportRow {
+    spec: &'static str,
+    name: String,
+    input_size: usize,
+    static_gas: u64,
+    dynamic_gas: u64,
+    gas: u64,
+    cycles: f64,
+    cycles_per_gas: f64,
+    /// Cycles per gas relative to the reference opcode, above 1 is under-priced.
+    relative: f64,
+}
+
+impl GasReportCommand {
+    /// Ranks every operation of the LUTs by its cycles per gas, relative to the reference opcode.
+    pub fn run(self) {
+        let luts = LutRegistry::load(self.opcode_lut.as_deref(), self.precompile_lut.as_deref())
+            .unwrap_or_else(|e| panic!("failed to load cycle LUTs: {e}"));
+        let specs = if self.spec.is_empty() {
+            OPCODE_TEST_VECTORS
+                .values()
+                .chain(PRECOMPILE_TEST_VECTORS.values())
+                .map(|builder| builder.spec_id())
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect_vec()
+        } else {
+            self.spec.clone()
+        };
+
+        let mut csv = csv::Writer::from_path(&self.csv).unwrap();
+        let mut markdown = String::new();
+        for spec in specs {
+            let rows = self.rank(&luts, spec);
+            for row in &rows {
+                csv.serialize(row).unwrap();
+            }
+            self.write_markdown(&mut markdown, spec, &rows);
+        }
+        csv.flush().unwrap();
+        fs::write(&self.markdown, markdown).expect("failed to write markdown report");
+    }
+
+    /// Prices every operation at the spec, from the most under-priced to the most over-priced.
+    fn rank(&self, luts: &LutRegistry, spec: SpecId) -> Vec<ReportRow> {
+        let opcodes = luts.opcodes().map(|(op, model)| {
+            let input_size = input_size_of(OPCODE_TEST_VECTORS.get(&op), self.input_size);
+            let gas = opcode_gas(op, spec, input_size, self.access);
+            let params = CycleParams::from_input_size(input_size);
+            (op.as_str(), input_size, gas, model, params)
+        });
+        let precompiles = luts.precompiles().map(|(name, model)| {
+            let builder = PRECOMPILE_TEST_VECTORS.get(name);
+            let input_size = input_size_of(builder, self.input_size);
+            let params = builder.map_or_else(
+                || CycleParams::from_input_size(input_size),
+                |builder| builder.cycle_params(input_size),
+            );
+            (
+                name,
+                input_size,
+                precompile_gas(name, spec, input_size),
+                model,
+                params,
+            )
+        });
+
+        let mut rows = opcodes
+            .chain(precompiles)
+            .filter_map(|(name, input_size, gas, model, params)| {
+                // free operations have no cycles per gas to compare
+                let GasCost {
+                    static_gas,
+                    dynamic_gas,
+                } = gas.filter(|gas| gas.total() > 0)?;
+                let cycles = model.estimate_cycle_count(&params);
+                Some(ReportRow {
+                    spec: spec.into(),
+                    name: name.to_string(),
+                    input_size,
+                    static_gas,
+                    dynamic_gas,
+                    gas: static_gas + dynamic_gas,
+                    cycles,
+                    cycles_per_gas: cycles / (static_gas + dynamic_gas) as f64,
+                    relative: 0.0,
+                })
+            })
+            .collect_vec();
+
+        let reference = rows
+            .iter()
+            .find(|row| row.name == self.reference.as_str())
+            .map(|row| row.cycles_per_gas)
+            .unwrap_or_else(|| {
+                panic!(
+                    "reference opcode {} is not priced at {}",
+                    self.reference.as_str(),
+                    <&'static str>::from(spec)
+                )
+            });
+        for row in &mut rows {
+            row.relative = row.cycles_per_gas / reference;
+        }
+        rows.sort_by(|a, b| b.relative.total_cmp(&a.relative));
+        rows
+    }
+
+    fn write_markdown(&self, out: &mut String, spec: SpecId, rows: &[ReportRow]) {
+        let spec: &'static str = spec.into();
+        writeln!(out, "## {spec}\n").unwrap();
+        writeln!(
+            out,
+            "Cycles per gas relative to {}, with {:?} access.\n",
+            self.reference.as_str(),
+            self.access
+        )
+        .unwrap();
+        let top = self.top.min(rows.len());
+        for (title, rows) in [
+            ("Under-priced", rows.iter().take(top).collect_vec()),
+            ("Over-priced", rows.iter().rev().take(top).collect_vec()),
+        ] {
+            writeln!(out, "### {title}\n").unwrap();
+            writeln!(
+                out,
+                "| Operation | Input size | Gas | Cycles | Cycles/gas | Relative |"
+            )
+            .unwrap();
+            writeln!(out, "|---|---:|---:|---:|---:|---:|").unwrap();
+            for row in rows {
+                writeln!(
+                    out,
+                    "| {} | {} | {} | {:.0} | {:.2} | {:.2} |",
+                    row.name, row.input_size, row.gas, row.cycles, row.cycles_per_gas, row.relative
+                )
+                .unwrap();
+            }
+            writeln!(out).unwrap();
+        }
+    }
+}
+
+/// The input size an operation is priced at, the given one or the median size of its test vector.
+pub(super) fn input_size_of(
+    builder: Option<&Arc<TestCaseBuilder>>,
+    input_size: Option<usize>,
+) -> usize {
+    match builder.map(|builder| builder.input_sizes()) {
+        Some([input_size]) => *input_size,
+        Some(input_sizes) if input_size.is_none() && !input_sizes.is_empty() => {
+            input_sizes[input_sizes.len() / 2]
+        }
+        _ => input_size.unwrap_or(1),
+    }
+}
+
+pub(super) fn parse_spec(s: &str) -> Result<SpecId, String> {
+    s.parse().map_err(|_| format!("unknown spec {s}"))
+}
+
+pub(super) fn parse_opcode(s: &str) -> Result<OpCode, String> {
+    OpCode::parse(s).ok_or_else(|| format!("unknown opcode {s}"))
+}
@@ -0,0 +1,230 @@
+use crate::commands::gas_report::{input_size_of, parse_opcode, parse_spec};
+use clap::Args;
+use evm_guest::SpecId;
+use revm_bytecode::OpCode;
+use serde::Serialize;
+use std::{collections::BTreeMap, fmt::Write, fs, path::PathBuf};
+use test_vector::{
+    CycleModel, CycleParams, LutRegistry, OPCODE_TEST_VECTORS, PRECOMPILE_TEST_VECTORS,
+    gas::{Access, GasCost, PricingRules, Rounding, dynamic_gas_unit, opcode_gas, precompile_gas},
+};
+
+#[derive(Debug, Args)]
+pub struct GasScheduleCommand {
+    /// Spec the mainnet prices are taken from, defaults to the latest spec of the test vectors
+    #[clap(long, value_parser = parse_spec)]
+    spec: Option<SpecId>,
+    /// Cycles a unit of gas pays for, defaults to the cycles per gas of the reference opcode
+    #[clap(long)]
+    cycles_per_gas: Option<f64>,
+    /// Opcode keeping its mainnet price when no cycles per gas target is given
+    #[clap(long, default_value = "ADD", value_parser = parse_opcode)]
+    reference: OpCode,
+    #[clap(long, value_enum, default_value_t)]
+    rounding: Rounding,
+    /// Prices are rounded to a multiple of this
+    #[clap(long, default_value_t = 1)]
+    granularity: u64,
+    /// Lowest static price of an operation
+    #[clap(long, default_value_t = 1)]
+    floor: u64,
+    /// Keep the mainnet price when the proposed one is within this many percent of it
+    #[clap(long, default_value_t = 0.0)]
+    keep_within: f64,
+    #[clap(long, value_enum, default_value_t)]
+    access: Access,
+    /// Input size dynamic operations are linearised at, defaults to the median size of their
+    /// test vector
+    #[clap(long)]
+    input_size: Option<usize>,
+    #[clap(long, default_value = "gas-schedule.json")]
+    out: PathBuf,
+    #[clap(long, default_value = "gas-schedule.md")]
+    markdown: PathBuf,
+    /// Opcode cycle LUT used instead of the embedded one
+    #[clap(long, env = "OPCODE_CYCLE_LUT")]
+    opcode_lut: Option<PathBuf>,
+    /// Precompile cycle LUT used instead of the embedded one
+    #[clap(long, env = "PRECOMPILE_CYCLE_LUT")]
+    precompile_lut: Option<PathBuf>,
+}
+
+/// A candidate gas table, the dynamic gas being charged per unit of input size, per word for
+/// the opcodes charged by word.
+#[derive(Debug, Serialize)]
+struct GasSchedule {
+    spec: &'static str,
+    cycles_per_gas: f64,
+    opcodes: BTreeMap<String, GasEntry>,
+    precompiles: BTreeMap<String, GasEntry>,
+}
+
+#[derive(Debug, Serialize)]
+struct GasEntry {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    opcode: Option<u8>,
+    static_gas: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dynamic_gas: Option<u64>,
+    #[serde(skip)]
+    mainnet: (u64, Option<u64>),
+}
+
+impl GasScheduleCommand {
+    /// Scales the cycle model of every operation of the LUTs to gas.
+    ///
+    /// Models and mainnet prices growing with the input size are linearised at the input size
+    /// of the operation, into a static price and a price per unit.
+    pub fn run(self) {
+        let luts = LutRegistry::load(self.opcode_lut.as_deref(), self.precompile_lut.as_deref())
+            .unwrap_or_else(|e| panic!("failed to load cycle LUTs: {e}"));
+        let spec = self.spec.unwrap_or_else(|| {
+            OPCODE_TEST_VECTORS
+                .values()
+                .chain(PRECOMPILE_TEST_VECTORS.values())
+                .map(|builder| builder.spec_id())
+                .max()
+                .expect("no test vectors")
+        });
+        let cycles_per_gas = self.cycles_per_gas.unwrap_or_else(|| {
+            let reference = self.reference;
+            let model = luts
+                .opcode(reference)
+                .unwrap_or_else(|| panic!("no cycle model for {}", reference.as_str()));
+            let gas = opcode_gas(reference, spec, 1, self.access)
+                .filter(|gas| gas.total() > 0)
+                .unwrap_or_else(|| panic!("{} is not priced", reference.as_str()));
+            model.estimate_cycle_count(&CycleParams::from_input_size(1)) / gas.total() as f64
+        });
+        let rules = PricingRules {
+            cycles_per_gas,
+            rounding: self.rounding,
+            granularity: self.granularity,
+            floor: self.floor,
+            tolerance: self.keep_within / 100.0,
+        };
+
+        let opcodes = luts
+            .opcodes()
+            .filter_map(|(op, model)| {
+                let input_size = input_size_of(OPCODE_TEST_VECTORS.get(&op), self.input_size);
+                let gas = |size| opcode_gas(op, spec, size, self.access);
+                let params = CycleParams::from_input_size;
+                let mut entry =
+                    price(&rules, model, params, input_size, dynamic_gas_unit(op), gas)?;
+                entry.opcode = Some(op.get());
+                Some((op.as_str().to_string(), entry))
+            })
+            .collect::<Vec<_>>();
+        let precompiles = luts
+            .precompiles()
+            .filter_map(|(name, model)| {
+                let builder = PRECOMPILE_TEST_VECTORS.get(name);
+                let input_size = input_size_of(builder, self.input_size);
+                let params = |size| {
+                    builder.map_or_else(
+                        || CycleParams::from_input_size(size),
+                        |builder| builder.cycle_params(size),
+                    )
+                };
+                let gas = |size| precompile_gas(name, spec, size);
+                let entry = price(&rules, model, params, input_size, 1, gas)?;
+                Some((name.to_string(), entry))
+            })
+            .collect::<Vec<_>>();
+
+        let mut markdown = String::new();
+        writeln!(
+            markdown,
+            "## Proposed gas schedule ({})\n",
+            <&str>::from(spec)
+        )
+        .unwrap();
+        writeln!(
+            markdown,
+            "Priced at {cycles_per_gas:.2} cycles per gas, rounded {:?} to multiples of {} with \
+             a floor of {} gas, mainnet prices within {}% are kept. Dynamic gas is charged per \
+             unit of input size, per word for the opcodes charged by word.\n",
+            self.rounding, self.granularity, self.floor, self.keep_within
+        )
+        .unwrap();
+        write_table(&mut markdown, "Opcodes", &opcodes);
+        write_table(&mut markdown, "Precompiles", &precompiles);
+        fs::write(&self.markdown, markdown).expect("failed to write markdown schedule");
+
+        let schedule = GasSchedule {
+            spec: spec.into(),
+            cycles_per_gas,
+            opcodes: opcodes.into_iter().collect(),
+            precompiles: precompiles.into_iter().collect(),
+        };
+        fs::write(&self.out, serde_json::to_string_pretty(&schedule).unwrap())
+            .expect("failed to write gas schedule");
+    }
+}
+
+/// Prices a single operation, evaluating its model on the parameters at an input size, `None` if
+/// it has no mainnet price at the spec.
+fn price(
+    rules: &PricingRules,
+    model: &CycleModel,
+    params: impl Fn(usize) -> CycleParams,
+    input_size: usize,
+    unit: usize,
+    gas: impl Fn(usize) -> Option<GasCost>,
+) -> Option<GasEntry> {
+    let cycles = |size| model.estimate_cycle_count(&params(size));
+    let mainnet_static = gas(0)?.total();
+    let mainnet_at_size = gas(input_size)?.total();
+    let dynamic = input_size > 0
+        && (!matches!(model, CycleModel::Constant { .. }) || mainnet_at_size != mainnet_static);
+    if !dynamic {
+        return Some(GasEntry {
+            opcode: None,
+            static_gas: rules.price(cycles(input_size), mainnet_at_size),
+            dynamic_gas: None,
+            mainnet: (mainnet_at_size, None),
+        });
+    }
+
+    let per_unit =
+        |at_size: f64, at_zero: f64| (at_size - at_zero) * unit as f64 / input_size as f64;
+    let mainnet_dynamic = per_unit(mainnet_at_size as f64, mainnet_static as f64).round() as u64;
+    // the price per unit has no floor, a zero slope is a valid price
+    let dynamic_rules = PricingRules { floor: 0, ..*rules };
+    Some(GasEntry {
+        opcode: None,
+        static_gas: rules.price(cycles(0), mainnet_static),
+        dynamic_gas: Some(
+            dynamic_rules.price(per_unit(cycles(input_size), cycles(0)), mainnet_dynamic),
+        ),
+        mainnet: (mainnet_static, Some(mainnet_dynamic)),
+    })
+}
+
+fn write_table(out: &mut String, title: &str, entries: &[(String, GasEntry)]) {
+    let optional = |gas: Option<u64>| gas.map(|gas| gas.to_string()).unwrap_or_default();
+    writeln!(out, "### {title}\n").unwrap();
+    writeln!(
+        out,
+        "| Opcode | Name | Mainnet gas | Proposed gas | Mainnet dynamic gas | Proposed dynamic gas |"
+    )
+    .unwrap();
+    writeln!(out, "|---|---|---:|---:|---:|---:|").unwrap();
+    for (name, entry) in entries {
+        writeln!(
+            out,
+            "| {} | {name} | {} | {} | {} | {} |",
+            entry
+                .opcode
+                .map(|op| format!("0x{op:02X}"))
+                .unwrap_or_default(),
+            entry.mainnet.0,
+            entry.static_gas,
+            optional(entry.mainnet.1),
+            optional(entry.dynamic_gas),
+        )
+        .unwrap();
+    }
+    writeln!(out).unwrap();
+}
@@ -0,0 +1,135 @@
+use crate::{GUEST_ELF, backend::Backend};
+use clap::ValueEnum;
+use evm_guest::primitives::{B256, hex, keccak256};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Records what produced a result set, written next to the output as `<out>.manifest.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Short hash identifying the run, LUTs generated from the results are stamped with it.
+    pub id: String,
+    pub git_revision: String,
+    pub guest_elf_hash: B256,
+    pub crate_versions: BTreeMap<String, String>,
+    pub args: Vec<String>,
+    pub backend: String,
+    /// Comma separated spec ids of the swept cases.
+    pub spec: String,
+    pub seed: u64,
+    pub seeds: Vec<u64>,
+    pub started_at: u64,
+    pub finished_at: Option<u64>,
+    pub host: Host,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Host {
+    pub os: String,
+    pub arch: String,
+    pub hostname: String,
+    pub parallelism: usize,
+}
+
+impl Manifest {
+    /// Writes the manifest of a starting run, a resumed run keeps the id of the previous one.
+    pub fn start(
+        out: &Path,
+        resume: bool,
+        backend: Backend,
+        spec: String,
+        seed: u64,
+        seeds: Vec<u64>,
+    ) -> Self {
+        let path = manifest_path(out);
+        if resume && path.exists() {
+            let mut manifest: Manifest =
+                serde_json::from_slice(&fs::read(&path).unwrap()).expect("invalid manifest");
+            manifest.finished_at = None;
+            manifest.write(out);
+            return manifest;
+        }
+
+        let mut manifest = Manifest {
+            id: String::new(),
+            git_revision: env!("GIT_REVISION").to_string(),
+            guest_elf_hash: keccak256(GUEST_ELF),
+            crate_versions: crate_versions(),
+            args: std::env::args().collect(),
+            backend: backend.to_possible_value().unwrap().get_name().to_string(),
+            spec,
+            seed,
+            seeds,
+            started_at: unix_timestamp(),
+            finished_at: None,
+            host: Host::current(),
+        };
+        let digest = keccak256(serde_json::to_vec(&manifest).unwrap());
+        manifest.id = hex::encode(&digest[..8]);
+        manifest.write(out);
+        manifest
+    }
+
+    /// Reads the id of the manifest written next to `out`, if any.
+    pub fn read_id(out: &Path) -> Option<String> {
+        let manifest: Manifest =
+            serde_json::from_slice(&fs::read(manifest_path(out)).ok()?).expect("invalid manifest");
+        Some(manifest.id)
+    }
+
+    pub fn finish(mut self, out: &Path) {
+        self.finished_at = Some(unix_timestamp());
+        self.write(out);
+    }
+
+    fn write(&self, out: &Path) {
+        fs::write(
+            manifest_path(out),
+            serde_json::to_string_pretty(self).unwrap(),
+        )
+        .expect("failed to write manifest");
+    }
+}
+
+impl Host {
+    fn current() -> Self {
+        Self {
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            hostname: fs::read_to_string("/proc/sys/kernel/hostname")
+                .ok()
+                .or_else(|| std::env::var("HOSTNAME").ok())
+                .map(|hostname| hostname.trim().to_string())
+                .unwrap_or_else(|| "unknown".to_string()),
+            parallelism: thread::available_parallelism().map_or(1, |n| n.get()),
+        }
+    }
+}
+
+fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// Versions of the revm and sp1 crates the runner was built with.
+pub(super) fn crate_versions() -> BTreeMap<String, String> {
+    env!("CRATE_VERSIONS")
+        .split(',')
+        .filter_map(|entry| entry.split_once('='))
+        .map(|(name, version)| (name.to_string(), version.to_string()))
+        .collect()
+}
+
+fn manifest_path(out: &Path) -> PathBuf {
+    let mut path = out.as_os_str().to_owned();
+    path.push(".manifest.json");
+    PathBuf::from(path)
+}
@@ -1,15 +1,27 @@
+use crate::backend::{Backend, DEFAULT_CROSS_CHECK_INTERVAL, set_cross_check_interval};
+use checkpoint::{CaseKey, Checkpoints};
 use clap::{Args, Subcommand};
+use failures::{CaseFailure, FailureLog, panic_message};
+use fit::Convergence;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use itertools::Itertools;
+use manifest::Manifest;
 use rand::{Rng, SeedableRng};
 use rand_xoshiro::Xoshiro256Plus;
 use rayon::iter::{ParallelBridge, ParallelIterator};
+use results::{ResultWriter, read_results};
+use runner::CaseError;
 use std::{
-    path::PathBuf,
-    sync::{Arc, LazyLock, Mutex},
+    collections::BTreeMap,
+    panic::{AssertUnwindSafe, catch_unwind},
+    path::{Path, PathBuf},
+    sync::{
+        Arc, LazyLock, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
     time::Duration,
 };
-use test_vector::{OpCodeOrPrecompile, TestCaseBuilder, TestCaseKind};
+use test_vector::{LutRegistry, OpCodeOrPrecompile, Sampling, TestCaseBuilder};
 
 static PROGRESS_STYLE: LazyLock<ProgressStyle> = LazyLock::new(|| {
     ProgressStyle::with_template(
@@ -18,20 +30,44 @@ static PROGRESS_STYLE: LazyLock<ProgressStyle> = LazyLock::new(|| {
         .unwrap()
 });
 
+mod all;
+mod bundle;
+mod checkpoint;
+mod estimate;
+mod failures;
+mod fit;
+mod gas_report;
+mod gas_schedule;
 mod jumpdest;
+mod manifest;
 mod opcodes;
 mod precompile;
+mod replay;
+mod results;
 mod runner;
+mod solve;
+mod validate;
 
 #[derive(Debug, Subcommand)]
 pub enum Commands {
     Opcodes(opcodes::OpcodesCommand),
     Precompile(precompile::PrecompileCommand),
     JumpDest(jumpdest::JumpDestCommand),
+    Fit(fit::FitCommand),
+    All(all::AllCommand),
+    Solve(solve::SolveCommand),
+    GasReport(gas_report::GasReportCommand),
+    GasSchedule(gas_schedule::GasScheduleCommand),
+    Estimate(estimate::EstimateCommand),
+    Validate(validate::ValidateCommand),
+    Replay(replay::ReplayCommand),
+    Bundle(bundle::BundleCommand),
 }
 
-#[derive(Debug, Args)]
+#[derive(Debug, Clone, Args)]
 pub struct CommonArgs {
+    /// Output path, a `.sqlite` or `.db` extension writes into a results database instead of csv,
+    /// which stays the default for the analysis scripts
     #[clap(long, default_value = "results.csv")]
     out: PathBuf,
     #[clap(long, default_value_t = 42)]
@@ -40,6 +76,42 @@ pub struct CommonArgs {
     repeat: usize,
     #[clap(long)]
     no_cache: bool,
+    #[clap(long, value_enum, default_value_t)]
+    backend: Backend,
+    /// Re-run every n-th case of the rv32im backend with the SP1 executor and compare the counts,
+    /// 0 disables
+    #[clap(long, default_value_t = DEFAULT_CROSS_CHECK_INTERVAL)]
+    cross_check: usize,
+    /// Skip the cases already written by a previous run with the same output
+    #[clap(long)]
+    resume: bool,
+    /// How the repetitions and input sizes of a case are chosen for each seed
+    #[clap(long, value_enum, default_value_t)]
+    sampling: Sampling,
+    /// Number of repetition and input size pairs measured per case and seed, for the random and
+    /// stratified sampling
+    #[clap(long, default_value_t = 64)]
+    budget: usize,
+    /// Stop measuring a case once the confidence interval of its fitted model is narrower than
+    /// this, relative to its center, 0 measures every sample
+    #[clap(long, default_value_t = 0.0)]
+    ci_tolerance: f64,
+    /// Samples of a case measured before its model is fitted to check the confidence interval
+    #[clap(long, default_value_t = 16)]
+    min_samples: usize,
+    /// Opcode cycle LUT used instead of the embedded one
+    #[clap(long, env = "OPCODE_CYCLE_LUT")]
+    opcode_lut: Option<PathBuf>,
+    /// Precompile cycle LUT used instead of the embedded one
+    #[clap(long, env = "PRECOMPILE_CYCLE_LUT")]
+    precompile_lut: Option<PathBuf>,
+}
+
+impl CommonArgs {
+    pub fn lut_registry(&self) -> LutRegistry {
+        LutRegistry::load(self.opcode_lut.as_deref(), self.precompile_lut.as_deref())
+            .unwrap_or_else(|e| panic!("failed to load cycle LUTs: {e}"))
+    }
 }
 
 impl Commands {
@@ -48,23 +120,90 @@ impl Commands {
             Commands::Opcodes(cmd) => cmd.run(),
             Commands::Precompile(cmd) => cmd.run(),
             Commands::JumpDest(jumpdest) => jumpdest.run(),
+            Commands::Fit(fit) => fit.run(),
+            Commands::All(all) => all.run(),
+            Commands::Solve(solve) => solve.run(),
+            Commands::GasReport(report) => report.run(),
+            Commands::GasSchedule(schedule) => schedule.run(),
+            Commands::Estimate(estimate) => estimate.run(),
+            Commands::Validate(validate) => validate.run(),
+            Commands::Replay(replay) => replay.run(),
+            Commands::Bundle(bundle) => bundle.run(),
         }
     }
 }
 
-fn opcodes_precompile_run_inner<C>(out: PathBuf, seed: u64, repeat: usize, cases: C)
+fn opcodes_precompile_run_inner<C>(common: CommonArgs, luts: &LutRegistry, cases: C)
 where
     C: Iterator<Item = (OpCodeOrPrecompile, Arc<TestCaseBuilder>)> + Send + Sync + Clone,
 {
-    let writer = Mutex::new(csv::Writer::from_path(out).unwrap());
+    let CommonArgs {
+        out,
+        seed,
+        repeat,
+        backend,
+        cross_check,
+        resume,
+        sampling,
+        budget,
+        ci_tolerance,
+        min_samples,
+        ..
+    } = common;
+
+    set_cross_check_interval(cross_check);
+
+    let checkpoints = Checkpoints::open(&out, resume);
+    if resume {
+        eprintln!("Resuming, {} cases already done.", checkpoints.len());
+    }
     let seeds = Xoshiro256Plus::seed_from_u64(seed)
         .random_iter()
         .take(repeat)
         .collect::<Vec<u64>>();
+    let spec = cases
+        .clone()
+        .map(|(_, builder)| <&'static str>::from(builder.spec_id()))
+        .unique()
+        .join(",");
+    let mixed_kinds = cases
+        .clone()
+        .map(|(_, builder)| builder.kind())
+        .unique()
+        .count()
+        > 1;
+    let manifest = Manifest::start(&out, resume, backend, spec, seed, seeds.clone());
+    let writer = Mutex::new(ResultWriter::open(&out, resume, &manifest, mixed_kinds));
+    let failures = Mutex::new(FailureLog::open(&out, resume));
+    let measured = AtomicUsize::new(0);
+    // name -> samples of the case, only tracked when stopping early
+    let convergence = Mutex::new(if ci_tolerance > 0.0 {
+        cases
+            .clone()
+            .map(|(name, builder)| {
+                let convergence = Convergence::new(builder.input_sizes().len());
+                (name.as_str().to_string(), convergence)
+            })
+            .collect()
+    } else {
+        BTreeMap::new()
+    });
+    let is_converged = |name: &OpCodeOrPrecompile| {
+        convergence
+            .lock()
+            .unwrap()
+            .get(name.as_str())
+            .is_some_and(Convergence::is_converged)
+    };
+    let mut resumed = if resume && ci_tolerance > 0.0 {
+        resumed_samples(&out, &seeds)
+    } else {
+        BTreeMap::new()
+    };
 
     let cases_length = cases
         .clone()
-        .map(|(_, builder)| builder.testcases_len())
+        .map(|(_, builder)| builder.sample_len(sampling, budget))
         .sum::<usize>();
 
     let m = MultiProgress::new();
@@ -81,42 +220,227 @@ where
     );
     tasks_pb.enable_steady_tick(Duration::from_millis(200));
 
-    cases
-        .cartesian_product(seeds)
-        .enumerate()
-        .par_bridge()
-        .panic_fuse()
-        .for_each(move |(idx, ((name, builder), seed))| {
-            let pb = m.add(
-                ProgressBar::new(cases_length as u64)
-                    .with_prefix(format!("#{idx:<03}"))
-                    .with_style(PROGRESS_STYLE.clone()),
-            );
-
-            let tcs = builder.build_all(Some(seed));
-            pb.set_message(builder.description().to_string());
+    // the seeds are measured in batches, convergence is only decided between them so the samples
+    // measured of a case do not depend on how the batch was scheduled
+    for (batch, seed) in seeds.iter().copied().enumerate() {
+        let pb = m.add(
+            ProgressBar::new(cases_length as u64)
+                .with_prefix(format!("#{batch:<03}"))
+                .with_style(PROGRESS_STYLE.clone()),
+        );
+        pb.set_message(format!("seed {seed}"));
 
-            for tc in tcs.into_iter() {
-                let result = runner::run_test(name.clone(), tc);
-                let mut writer = writer.lock().unwrap();
-                match builder.kind() {
-                    TestCaseKind::ConstantSimple => {
-                        writer.serialize(result.to_constant_simple_case_result())
-                    }
-                    TestCaseKind::ConstantMixed => {
-                        writer.serialize(result.to_constant_mixed_case_result())
-                    }
-                    TestCaseKind::DynamicSimple => {
-                        writer.serialize(result.to_dynamic_simple_case_result())
-                    }
-                    TestCaseKind::DynamicMixed => {
-                        writer.serialize(result.to_dynamic_mixed_case_result())
+        cases
+            .clone()
+            .flat_map(|(name, builder)| {
+                builder
+                    .sample_params(sampling, budget, seed)
+                    .into_iter()
+                    .map(move |params| (name.clone(), builder.clone(), params))
+            })
+            .par_bridge()
+            .for_each(|(name, builder, (repetition, input_size))| {
+                let key = CaseKey {
+                    name: name.clone(),
+                    seed,
+                    repetition,
+                    input_size,
+                };
+                if is_converged(&name) {
+                    // a resumed run skips it as well
+                    checkpoints.mark_converged(&key);
+                } else if !checkpoints.is_done(&key) {
+                    let result = measure_case(
+                        key,
+                        &checkpoints,
+                        &failures,
+                        || {
+                            let tc = builder.build(repetition, input_size, Some(seed));
+                            runner::run_test(backend, luts, name.clone(), tc)
+                        },
+                        |key, result| {
+                            writer
+                                .lock()
+                                .unwrap()
+                                .write(key, result)
+                                .map_err(CaseError::Write)
+                        },
+                    );
+                    if let Some(result) = result {
+                        measured.fetch_add(1, Ordering::Relaxed);
+                        // cases without an instruction count never converge
+                        if let Some(delta) = result.instruction_delta_per_op() {
+                            if let Some(convergence) =
+                                convergence.lock().unwrap().get_mut(name.as_str())
+                            {
+                                convergence.add(input_size, delta);
+                            }
+                        }
                     }
                 }
-                .unwrap();
                 pb.inc(1);
                 tasks_pb.inc(1);
+            });
+        pb.finish_and_clear();
+
+        let mut cases_convergence = convergence.lock().unwrap();
+        for (name, input_size, delta) in resumed.remove(&batch).unwrap_or_default() {
+            if let Some(convergence) = cases_convergence.get_mut(&name) {
+                convergence.add(input_size, delta);
             }
-            pb.finish_and_clear();
-        });
+        }
+        for convergence in cases_convergence.values_mut() {
+            convergence.update(ci_tolerance, min_samples);
+        }
+    }
+
+    checkpoints.flush();
+    manifest.finish(&out);
+
+    for (name, convergence) in convergence.into_inner().unwrap() {
+        if convergence.is_converged() {
+            eprintln!(
+                "{name} converged after {} samples, stopped sampling it.",
+                convergence.len()
+            );
+        }
+    }
+    failures
+        .into_inner()
+        .unwrap()
+        .print_summary(measured.into_inner());
+}
+
+/// The samples measured by the run being resumed by the index of their seed, replayed into the
+/// convergence of their batch so that it is decided as in an uninterrupted run.
+fn resumed_samples(out: &Path, seeds: &[u64]) -> BTreeMap<usize, Vec<(String, usize, f64)>> {
+    let mut samples = BTreeMap::<_, Vec<_>>::new();
+    if !out.exists() {
+        return samples;
+    }
+    for record in read_results(out) {
+        let batch = record
+            .seed
+            .and_then(|seed| seeds.iter().position(|s| *s == seed));
+        if let (Some(batch), Some(delta)) = (batch, record.instruction_delta_per_op()) {
+            let input_size = record.input_size.unwrap_or_default();
+            samples
+                .entry(batch)
+                .or_default()
+                .push((record.name, input_size, delta));
+        }
+    }
+    samples
+}
+
+/// Measures and records a case, a panic while doing so is a failure like any other. A recorded
+/// case is marked as done, a failed one is logged and not marked, so a resumed run measures it
+/// again.
+fn measure_case<T>(
+    key: CaseKey,
+    checkpoints: &Checkpoints,
+    failures: &Mutex<FailureLog>,
+    measure: impl FnOnce() -> Result<T, CaseError>,
+    record: impl FnOnce(&CaseKey, &T) -> Result<(), CaseError>,
+) -> Option<T> {
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        let result = measure()?;
+        record(&key, &result)?;
+        Ok(result)
+    }))
+    .unwrap_or_else(|payload| Err(CaseError::Panic(panic_message(&*payload))));
+    match result {
+        Ok(result) => {
+            // recorded above, the row is durable before the case is marked as done
+            checkpoints.mark_done(&key);
+            Some(result)
+        }
+        Err(error) => {
+            failures.lock().unwrap().write(&CaseFailure { key, error });
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use revm_bytecode::OpCode;
+    use std::fs;
+
+    #[test]
+    fn failed_case_is_retried_on_resume() {
+        let out = std::env::temp_dir().join(format!("runner-failed-case-{}", std::process::id()));
+        let failures_path = PathBuf::from(format!("{}.failures.jsonl", out.display()));
+        let key = CaseKey {
+            name: OpCodeOrPrecompile::OpCode(OpCode::ADD),
+            seed: 1,
+            repetition: 2,
+            input_size: 0,
+        };
+
+        {
+            let checkpoints = Checkpoints::open(&out, false);
+            let failures = Mutex::new(FailureLog::open(&out, false));
+            let measured = measure_case(
+                key.clone(),
+                &checkpoints,
+                &failures,
+                || -> Result<(), CaseError> { panic!("case panicked") },
+                |_, _| panic!("a failed case is not recorded"),
+            );
+            assert!(measured.is_none());
+            assert!(!checkpoints.is_done(&key));
+
+            let unwritten = CaseKey {
+                repetition: 3,
+                ..key.clone()
+            };
+            let measured = measure_case(
+                unwritten.clone(),
+                &checkpoints,
+                &failures,
+                || Ok(()),
+                |_, _| Err(CaseError::Write("output is gone".to_string())),
+            );
+            assert!(measured.is_none());
+            assert!(!checkpoints.is_done(&unwritten));
+        }
+        let content = fs::read_to_string(&failures_path).unwrap();
+        let records = content
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0]["name"], "ADD");
+        assert_eq!(records[0]["kind"], "panic");
+        assert_eq!(records[0]["message"], "case panicked");
+        assert_eq!(records[1]["kind"], "write");
+
+        {
+            let checkpoints = Checkpoints::open(&out, true);
+            assert!(!checkpoints.is_done(&key));
+            let failures = Mutex::new(FailureLog::open(&out, true));
+            let mut recorded = false;
+            let measured = measure_case(
+                key.clone(),
+                &checkpoints,
+                &failures,
+                || Ok(()),
+                |_, _| {
+                    recorded = true;
+                    Ok(())
+                },
+            );
+            assert_eq!(measured, Some(()));
+            assert!(recorded);
+            assert!(checkpoints.is_done(&key));
+        }
+        // the failure of the first run is kept
+        assert_eq!(fs::read_to_string(&failures_path).unwrap(), content);
+
+        fs::remove_file(&failures_path).unwrap();
+        fs::remove_file(format!("{}.mismatches.jsonl", out.display())).unwrap();
+        fs::remove_dir_all(format!("{}.checkpoints", out.display())).unwrap();
+    }
 }
@@ -2,7 +2,7 @@ use crate::commands::{CommonArgs, opcodes_precompile_run_inner};
 use clap::Args;
 use revm_bytecode::OpCode;
 use std::collections::BTreeSet;
-use test_vector::{OPCODE_CYCLE_LUT, OPCODE_TEST_VECTORS, OpCodeOrPrecompile, TestCaseKind};
+use test_vector::{OPCODE_TEST_VECTORS, OpCodeOrPrecompile, TestCaseKind};
 
 #[derive(Debug, Args)]
 pub struct OpcodesCommand {
@@ -17,12 +17,8 @@ pub struct OpcodesCommand {
 
 impl OpcodesCommand {
     pub fn run(self) {
-        let CommonArgs {
-            out,
-            seed,
-            repeat,
-            no_cache,
-        } = self.common;
+        let no_cache = self.common.no_cache;
+        let luts = self.common.lut_registry();
 
         let opcodes = self
             .opcodes
@@ -36,9 +32,8 @@ impl OpcodesCommand {
         }
 
         opcodes_precompile_run_inner(
-            out,
-            seed,
-            repeat,
+            self.common,
+            &luts,
             OPCODE_TEST_VECTORS
                 .iter()
                 .filter(|(op, tc)| {
@@ -47,7 +42,7 @@ impl OpcodesCommand {
                         if no_cache {
                             tc.kind() == kind
                         } else {
-                            tc.kind() == kind && !OPCODE_CYCLE_LUT.contains_key(op)
+                            tc.kind() == kind && !luts.contains_opcode(**op)
                         }
                     } else {
                         opcodes.contains(op)
@@ -1,9 +1,7 @@
 use crate::commands::{CommonArgs, opcodes_precompile_run_inner};
 use clap::Args;
 use std::collections::BTreeSet;
-use test_vector::{
-    OpCodeOrPrecompile, PRECOMPILE_CYCLE_LUT, PRECOMPILE_TEST_VECTORS, TestCaseKind,
-};
+use test_vector::{OpCodeOrPrecompile, PRECOMPILE_TEST_VECTORS, TestCaseKind};
 
 #[derive(Debug, Args)]
 pub struct PrecompileCommand {
@@ -18,12 +16,8 @@ pub struct PrecompileCommand {
 
 impl PrecompileCommand {
     pub fn run(self) {
-        let CommonArgs {
-            out,
-            seed,
-            repeat,
-            no_cache,
-        } = self.common;
+        let no_cache = self.common.no_cache;
+        let luts = self.common.lut_registry();
 
         let names = self.names.into_iter().collect::<BTreeSet<_>>();
 
@@ -33,9 +27,8 @@ impl PrecompileCommand {
         }
 
         opcodes_precompile_run_inner(
-            out,
-            seed,
-            repeat,
+            self.common,
+            &luts,
             PRECOMPILE_TEST_VECTORS
                 .iter()
                 .filter(|(name, tc)| {
@@ -44,7 +37,7 @@ impl PrecompileCommand {
                         if no_cache {
                             tc.kind() == kind
                         } else {
-                            tc.kind() == kind && !PRECOMPILE_CYCLE_LUT.contains_key(name.as_ref())
+                            tc.kind() == kind && !luts.contains_precompile(name.as_ref())
                         }
                     } else {
                         names.contains(name.as_ref())
@@ -0,0 +1,116 @@
+use crate::{
+    backend::Backend,
+    commands::{bundle::StdinBundle, runner::run_test},
+};
+use clap::Args;
+use revm_bytecode::OpCode;
+use std::{path::PathBuf, process, sync::Arc};
+use test_vector::{
+    LutRegistry, OPCODE_TEST_VECTORS, OpCodeOrPrecompile, PRECOMPILE_TEST_VECTORS, TestCaseBuilder,
+};
+
+#[derive(Debug, Args)]
+pub struct ReplayCommand {
+    /// Opcode or precompile of the case
+    name: String,
+    /// Seed of the case, as written in the seed column of the results
+    #[clap(long)]
+    seed: u64,
+    #[clap(long)]
+    repetition: usize,
+    #[clap(long)]
+    input_size: usize,
+    #[clap(long, value_enum, default_value_t)]
+    backend: Backend,
+    /// Where the guest input of the measured execution is written as a bundle
+    #[clap(long)]
+    dump_stdin: Option<PathBuf>,
+    /// Opcode cycle LUT used instead of the embedded one
+    #[clap(long, env = "OPCODE_CYCLE_LUT")]
+    opcode_lut: Option<PathBuf>,
+    /// Precompile cycle LUT used instead of the embedded one
+    #[clap(long, env = "PRECOMPILE_CYCLE_LUT")]
+    precompile_lut: Option<PathBuf>,
+}
+
+impl ReplayCommand {
+    /// Rebuilds a single case of a sweep and measures it once, printing everything observed.
+    pub fn run(self) {
+        let luts = LutRegistry::load(self.opcode_lut.as_deref(), self.precompile_lut.as_deref())
+            .unwrap_or_else(|e| panic!("failed to load cycle LUTs: {e}"));
+        let (name, builder) =
+            find_case(&self.name).unwrap_or_else(|| panic!("no test vector named {}", self.name));
+        let build = || builder.build(self.repetition, self.input_size, Some(self.seed));
+
+        if let Some(path) = &self.dump_stdin {
+            StdinBundle::from_case(&self.name, &build()).write(path);
+            eprintln!("Guest input written to {}.", path.display());
+        }
+
+        let tc = build();
+        println!("{tc}, seed {}", self.seed);
+        let result = match run_test(self.backend, &luts, name, tc) {
+            Ok(result) => result,
+            Err(error) => {
+                eprintln!("Case failed ({}): {error}", error.kind());
+                process::exit(1);
+            }
+        };
+
+        for (title, measurement) in [("Baseline", result.baseline()), ("Exec", result.exec())] {
+            let instructions = measurement
+                .instruction_count
+                .map_or_else(|| "uncounted".to_string(), |count| count.to_string());
+            println!(
+                "{title}: {instructions} instructions in {:?}",
+                measurement.elapsed
+            );
+            if let Some(report) = &measurement.report {
+                println!("{report}");
+            }
+        }
+        println!(
+            "{} gas used, ended with {}",
+            result.gas_used(),
+            result.result()
+        );
+        println!("Opcodes: {}", result.opcodes_usage());
+        for (frame, usage) in result.opcodes_usage().frames() {
+            println!(
+                "  frame {} at {}: {}",
+                frame.depth,
+                frame.address,
+                usage
+                    .iter()
+                    .map(|(op, count)| format!("{}:{count}", op.as_str()))
+                    .collect::<Vec<_>>()
+                    .join(";")
+            );
+        }
+        for (address, input, calls) in result.opcodes_usage().precompile_calls() {
+            let lengths = input
+                .modexp_lengths
+                .map(|[base, exponent, modulus]| {
+                    format!(" (base {base}, exponent {exponent}, modulus {modulus})")
+                })
+                .unwrap_or_default();
+            println!(
+                "  precompile {address} with {} bytes{lengths}: {} calls, {} gas",
+                input.len, calls.count, calls.gas_used
+            );
+        }
+    }
+}
+
+/// The test vector of a precompile or an opcode by name.
+pub(super) fn find_case(name: &str) -> Option<(OpCodeOrPrecompile, Arc<TestCaseBuilder>)> {
+    if let Some((name, builder)) = PRECOMPILE_TEST_VECTORS.get_key_value(name) {
+        return Some((
+            OpCodeOrPrecompile::Precompile(name.clone()),
+            builder.clone(),
+        ));
+    }
+    let op = OpCode::parse(name)?;
+    let builder = OPCODE_TEST_VECTORS.get(&op)?;
+    Some((OpCodeOrPrecompile::OpCode(op), builder.clone()))
+}
@@ -0,0 +1,374 @@
+use super::{
+    checkpoint::{CaseKey, open_csv_writer},
+    manifest::Manifest,
+    runner::TestRunResult,
+};
+use clap::ValueEnum;
+use serde::Deserialize;
+use sqlx::{
+    SqlitePool,
+    sqlite::{SqliteConnectOptions, SqlitePoolOptions},
+};
+use std::{fs::File, path::Path};
+use test_vector::TestCaseKind;
+use tokio::runtime::Runtime;
+
+/// Where the results of a sweep are written, chosen by the extension of the output path. Csv
+/// stays the default as the analysis scripts read it with pandas, the results database is for
+/// querying, de-duplicating and joining runs.
+pub enum ResultWriter {
+    /// The columns of the kind of the cases, a sweep of a single kind.
+    Csv(csv::Writer<File>),
+    /// The columns of every kind, a csv row has a fixed width so a sweep mixing kinds can not
+    /// write the columns of each.
+    CaseRowCsv(csv::Writer<File>),
+    Sqlite(SqliteWriter),
+}
+
+/// A case result read back from an output, with the columns shared by every kind.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ResultRecord {
+    pub name: String,
+    /// Missing for cases built without a seed.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    pub repetition: usize,
+    /// Missing for constant cases written as csv.
+    #[serde(default)]
+    pub input_size: Option<usize>,
+    /// Missing when the backend could not count instructions.
+    #[serde(default)]
+    pub baseline_instruction_count: Option<u64>,
+    #[serde(default)]
+    pub exec_instruction_count: Option<u64>,
+    /// Only present for mixed cases.
+    #[serde(default)]
+    pub instruction_count_consumes_by_other_estimated: Option<f64>,
+    /// Missing from results written before the usage was recorded.
+    #[serde(default)]
+    pub opcode_usage: Option<String>,
+    /// Missing from results written before the gas was recorded.
+    #[serde(default)]
+    pub gas_used: Option<u64>,
+}
+
+/// name, seed, repetition, input size, baseline, exec, other estimated, opcode usage, gas used
+type ResultColumns = (
+    String,
+    Option<String>,
+    i64,
+    i64,
+    Option<i64>,
+    Option<i64>,
+    Option<f64>,
+    Option<String>,
+    Option<i64>,
+);
+
+pub struct SqliteWriter {
+    rt: Runtime,
+    pool: SqlitePool,
+    run_id: i64,
+}
+
+impl ResultWriter {
+    /// Opens the output, a `.sqlite` or `.db` path selects the results database, anything else
+    /// is written as csv.
+    pub fn open(out: &Path, resume: bool, manifest: &Manifest, mixed_kinds: bool) -> Self {
+        match out.extension().and_then(|ext| ext.to_str()) {
+            Some("sqlite" | "sqlite3" | "db") => {
+                ResultWriter::Sqlite(SqliteWriter::open(out, resume, manifest))
+            }
+            _ if mixed_kinds => ResultWriter::CaseRowCsv(open_csv_writer(out, resume)),
+            _ => ResultWriter::Csv(open_csv_writer(out, resume)),
+        }
+    }
+
+    /// Writes the result of a case, it is durable once this returns.
+    pub fn write(&mut self, key: &CaseKey, result: &TestRunResult) -> Result<(), String> {
+        match self {
+            ResultWriter::Csv(writer) => {
+                match result.kind() {
+                    TestCaseKind::ConstantSimple => {
+                        writer.serialize(result.to_constant_simple_case_result())
+                    }
+                    TestCaseKind::ConstantMixed => {
+                        writer.serialize(result.to_constant_mixed_case_result())
+                    }
+                    TestCaseKind::DynamicSimple => {
+                        writer.serialize(result.to_dynamic_simple_case_result())
+                    }
+                    TestCaseKind::DynamicMixed => {
+                        writer.serialize(result.to_dynamic_mixed_case_result())
+                    }
+                }
+                .map_err(|e| format!("failed to write result: {e}"))?;
+                writer
+                    .flush()
+                    .map_err(|e| format!("failed to write result: {e}"))
+            }
+            ResultWriter::CaseRowCsv(writer) => {
+                writer
+                    .serialize(result.to_case_row())
+                    .map_err(|e| format!("failed to write result: {e}"))?;
+                writer
+                    .flush()
+                    .map_err(|e| format!("failed to write result: {e}"))
+            }
+            ResultWriter::Sqlite(writer) => writer.insert(key, result),
+        }
+    }
+}
+
+impl SqliteWriter {
+    /// Opens the database and registers the run, a resumed run keeps appending to the latest one.
+    fn open(out: &Path, resume: bool, manifest: &Manifest) -> Self {
+        let rt = Runtime::new().unwrap();
+        let (pool, run_id) = rt.block_on(async {
+            let pool = open_database(out).await;
+
+            let last_run_id: Option<i64> = if resume {
+                sqlx::query_scalar("select max(id) from runs")
+                    .fetch_one(&pool)
+                    .await
+                    .unwrap()
+            } else {
+                None
+            };
+            let run_id = match last_run_id {
+                Some(run_id) => run_id,
+                None => sqlx::query_scalar(
+                    "insert into runs \
+                    (git_revision, seed, repeat, spec, backend, guest_elf_hash, started_at, \
+                    manifest_id) \
+                    values (?, ?, ?, ?, ?, ?, ?, ?) returning id",
+                )
+                .bind(&manifest.git_revision)
+                // seeds are u64, stored as text since an integer column holds the larger half
+                // of them as negative numbers
+                .bind(manifest.seed.to_string())
+                .bind(manifest.seeds.len() as i64)
+                .bind(&manifest.spec)
+                .bind(&manifest.backend)
+                .bind(manifest.guest_elf_hash.as_slice())
+                .bind(manifest.started_at as i64)
+                .bind(&manifest.id)
+                .fetch_one(&pool)
+                .await
+                .expect("failed to register run"),
+            };
+            (pool, run_id)
+        });
+        Self { rt, pool, run_id }
+    }
+
+    fn insert(&self, key: &CaseKey, result: &TestRunResult) -> Result<(), String> {
+        let row = result.to_case_row();
+        self.rt.block_on(async {
+            sqlx::query(
+                "insert or replace into results \
+                (run_id, case_key, name, seed, kind, repetition, input_size, \
+                baseline_instruction_count, exec_instruction_count, \
+                baseline_elapsed_ns, exec_elapsed_ns, \
+                instruction_count_consumes_by_other_estimated, opcode_usage, gas_used, result) \
+                values (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(self.run_id)
+            .bind(key.to_string())
+            .bind(row.name)
+            .bind(row.seed.map(|seed| seed.to_string()))
+            .bind(row.kind.to_possible_value().unwrap().get_name())
+            .bind(row.repetition as i64)
+            .bind(row.input_size as i64)
+            .bind(row.baseline_instruction_count.map(|count| count as i64))
+            .bind(row.exec_instruction_count.map(|count| count as i64))
+            .bind(row.baseline_elapsed_ns as i64)
+            .bind(row.exec_elapsed_ns as i64)
+            .bind(row.instruction_count_consumes_by_other_estimated)
+            .bind(row.opcode_usage)
+            .bind(row.gas_used as i64)
+            .bind(row.result)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("failed to insert result: {e}"))
+        })
+    }
+}
+
+impl ResultRecord {
+    /// Instructions the whole case spent over its baseline, `None` if the backend could not
+    /// count instructions.
+    pub fn instruction_delta(&self) -> Option<f64> {
+        Some(self.exec_instruction_count? as f64 - self.baseline_instruction_count? as f64)
+    }
+
+    /// Instructions spent per repetition of the measured opcode, excluding the estimated cost of
+    /// the other opcodes of a mixed case.
+    pub fn instruction_delta_per_op(&self) -> Option<f64> {
+        let delta = self.instruction_delta()?
+            - self
+                .instruction_count_consumes_by_other_estimated
+                .unwrap_or_default();
+        Some(delta / self.repetition as f64)
+    }
+
+    /// Instructions the whole case spent per unit of gas it used, `None` if the gas was not
+    /// recorded or the case is free.
+    pub fn instructions_per_gas(&self) -> Option<f64> {
+        let delta = self.instruction_delta()?;
+        self.gas_used
+            .filter(|gas_used| *gas_used > 0)
+            .map(|gas_used| delta / gas_used as f64)
+    }
+}
+
+/// Reads the results of an output, only the latest run is read from a results database.
+pub fn read_results(path: &Path) -> Vec<ResultRecord> {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("sqlite" | "sqlite3" | "db") => {
+            let rt = Runtime::new().unwrap();
+            rt.block_on(async {
+                let pool = open_database(path).await;
+                sqlx::query_as::<_, ResultColumns>(
+                    "select name, seed, repetition, input_size, \
+                    baseline_instruction_count, exec_instruction_count, \
+                    instruction_count_consumes_by_other_estimated, opcode_usage, gas_used \
+                    from results where run_id = (select max(id) from runs)",
+                )
+                .fetch_all(&pool)
+                .await
+                .expect("failed to read results")
+            })
+            .into_iter()
+            .map(
+                |(
+                    name,
+                    seed,
+                    repetition,
+                    input_size,
+                    baseline,
+                    exec,
+                    other_estimated,
+                    opcode_usage,
+                    gas_used,
+                )| ResultRecord {
+                    name,
+                    seed: seed.map(|seed| seed.parse().expect("invalid seed in results")),
+                    repetition: repetition as usize,
+                    input_size: Some(input_size as usize),
+                    baseline_instruction_count: baseline.map(|count| count as u64),
+                    exec_instruction_count: exec.map(|count| count as u64),
+                    instruction_count_consumes_by_other_estimated: other_estimated,
+                    opcode_usage,
+                    gas_used: gas_used.map(|gas_used| gas_used as u64),
+                },
+            )
+            .collect()
+        }
+        _ => csv::Reader::from_path(path)
+            .expect("failed to open results")
+            .deserialize()
+            .collect::<Result<_, _>>()
+            .expect("failed to read results"),
+    }
+}
+
+async fn open_database(path: &Path) -> SqlitePool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(
+            SqliteConnectOptions::new()
+                .filename(path)
+                .create_if_missing(true),
+        )
+        .await
+        .expect("failed to open results database");
+    sqlx::migrate!("./migrations")
+        .run(&pool)
+        .await
+        .expect("failed to run migrations");
+    pool
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{backend::Backend, commands::fit::fit_results};
+    use revm_bytecode::OpCode;
+    use std::fs;
+    use test_vector::{CycleModel, LutRegistry, OpCodeOrPrecompile, fitting::Aggregate};
+
+    #[test]
+    fn sweep_mixing_kinds_is_fitted() {
+        let out =
+            std::env::temp_dir().join(format!("runner-mixed-kinds-{}.csv", std::process::id()));
+        let luts = LutRegistry::load(None, None).unwrap();
+        let manifest = Manifest::start(&out, false, Backend::Native, "CANCUN".into(), 0, vec![0]);
+        let mut writer = ResultWriter::open(&out, false, &manifest, true);
+
+        // ADD costs 10 instructions, POP of a mixed case 20, EXP 5 plus 2 per byte of exponent
+        let mut results = vec![];
+        for repetition in 1..=8 {
+            results.push((
+                OpCodeOrPrecompile::OpCode(OpCode::ADD),
+                TestCaseKind::ConstantSimple,
+                (repetition, 0),
+                10 * repetition,
+            ));
+            results.push((
+                OpCodeOrPrecompile::OpCode(OpCode::POP),
+                TestCaseKind::ConstantMixed,
+                (repetition, 0),
+                20 * repetition,
+            ));
+            for input_size in 1..=4 {
+                results.push((
+                    OpCodeOrPrecompile::OpCode(OpCode::EXP),
+                    TestCaseKind::DynamicSimple,
+                    (repetition, input_size),
+                    (5 + 2 * input_size) * repetition,
+                ));
+            }
+        }
+        for (name, kind, (repetition, input_size), delta) in results {
+            let key = CaseKey {
+                name: name.clone(),
+                seed: u64::MAX,
+                repetition,
+                input_size,
+            };
+            let result = TestRunResult::measured(
+                &luts,
+                name,
+                kind,
+                (repetition, input_size),
+                (100, 100 + delta as u64),
+            );
+            writer.write(&key, &result).unwrap();
+        }
+        drop(writer);
+        manifest.finish(&out);
+
+        let records = read_results(&out);
+        assert_eq!(records.len(), 8 * 6);
+        assert!(records.iter().all(|record| record.seed == Some(u64::MAX)));
+
+        let lut = fit_results(&out, Aggregate::Median);
+        for (name, expected) in [("ADD", 10.0), ("POP", 20.0)] {
+            assert!(
+                matches!(lut[name].model, CycleModel::Constant { cycles } if cycles == expected),
+                "{name}: {:?}",
+                lut[name].model
+            );
+        }
+        let CycleModel::Linear { slope, intercept } = lut["EXP"].model else {
+            panic!("EXP: {:?}", lut["EXP"].model);
+        };
+        assert!((slope - 2.0).abs() < 1e-9 && (intercept - 5.0).abs() < 1e-9);
+
+        for suffix in ["", ".manifest.json"] {
+            fs::remove_file(format!("{}{suffix}", out.display())).unwrap();
+        }
+    }
+}
@@ -1,25 +1,60 @@
-use crate::{GUEST_ELF, JUMPDEST_GUEST_ELF};
+use crate::{
+    JUMPDEST_GUEST_ELF, TRANSACTION_GUEST_ELF,
+    backend::{Backend, CLIENT, Measurement},
+};
+use clap::ValueEnum;
+use evm_guest::{Address, InterpreterResult};
 use itertools::Itertools;
 use revm_bytecode::{Bytecode, OpCode};
 use revm_interpreter::interpreter::ExtBytecode;
-use serde::Serialize;
-use sp1_sdk::{CpuProver, ExecutionReport, SP1Stdin};
-use std::{mem, sync::LazyLock};
-use test_vector::{OPCODE_CYCLE_LUT, OpCodeOrPrecompile, OpcodeUsage, TestCase, TestCaseKind};
-
-pub(crate) static CLIENT: LazyLock<CpuProver> = LazyLock::new(CpuProver::new);
-
-pub struct TestRunResult {
+use serde::{Serialize, Serializer};
+use sp1_sdk::SP1Stdin;
+use std::{
+    fmt::{self, Display},
+    mem,
+    time::Instant,
+};
+use test_vector::{
+    CallTarget, LutRegistry, OpCodeOrPrecompile, OpcodeUsage, TestCase, TestCaseKind,
+    estimate::TransactionFixture,
+};
+
+pub struct TestRunResult<'a> {
     name: OpCodeOrPrecompile,
+    luts: &'a LutRegistry,
 
     kind: TestCaseKind,
+    seed: Option<u64>,
     repetition: usize,
     input_size: usize,
 
-    baseline_report: ExecutionReport,
-    exec_report: ExecutionReport,
-    // interpreter_result: InterpreterResult,
+    baseline: Measurement,
+    exec: Measurement,
+    interpreter_result: InterpreterResult,
     opcodes_usage: OpcodeUsage,
+    expected_precompile_call: Option<(Address, usize)>,
+}
+
+/// Why a case could not be measured.
+#[derive(Debug, Clone)]
+pub enum CaseError {
+    /// The backend failed to execute the case.
+    Execution(String),
+    /// The guest ended the case differently from the host.
+    Mismatch(ResultMismatch),
+    /// The measurement does not measure what the case is meant to.
+    SanityCheck(String),
+    /// Building, counting or measuring the case panicked.
+    Panic(String),
+    /// The result could not be written to the output.
+    Write(String),
+}
+
+/// The guest ended the case differently from the host.
+#[derive(Debug, Clone)]
+pub struct ResultMismatch {
+    pub guest: InterpreterResult,
+    pub host: InterpreterResult,
 }
 
 #[derive(Debug, Copy, Clone, Serialize)]
@@ -32,43 +67,101 @@ pub struct JumpdestResult {
 #[derive(Serialize)]
 pub struct ConstantSimpleCaseResult<'a> {
     name: &'a str,
+    seed: Option<u64>,
     repetition: usize,
-    baseline_instruction_count: u64,
-    exec_instruction_count: u64,
+    baseline_instruction_count: Option<u64>,
+    exec_instruction_count: Option<u64>,
+    baseline_elapsed_ns: u128,
+    exec_elapsed_ns: u128,
+    gas_used: u64,
+    result: String,
+    opcode_usage: String,
 }
 
 #[derive(Serialize)]
 pub struct ConstantMixedCaseResult<'a> {
     name: &'a str,
+    seed: Option<u64>,
     repetition: usize,
-    baseline_instruction_count: u64,
-    exec_instruction_count: u64,
+    baseline_instruction_count: Option<u64>,
+    exec_instruction_count: Option<u64>,
+    baseline_elapsed_ns: u128,
+    exec_elapsed_ns: u128,
     instruction_count_consumes_by_other_estimated: f64,
+    gas_used: u64,
+    result: String,
+    opcode_usage: String,
 }
 
 #[derive(Debug, Serialize)]
 pub struct DynamicSimpleCaseResult<'a> {
     name: &'a str,
+    seed: Option<u64>,
     repetition: usize,
     input_size: usize,
-    baseline_instruction_count: u64,
-    exec_instruction_count: u64,
+    baseline_instruction_count: Option<u64>,
+    exec_instruction_count: Option<u64>,
+    baseline_elapsed_ns: u128,
+    exec_elapsed_ns: u128,
+    gas_used: u64,
+    result: String,
+    opcode_usage: String,
 }
 
 #[derive(Serialize)]
 pub struct DynamicMixedCaseResult<'a> {
     name: &'a str,
+    seed: Option<u64>,
     repetition: usize,
     input_size: usize,
-    baseline_instruction_count: u64,
-    exec_instruction_count: u64,
+    baseline_instruction_count: Option<u64>,
+    exec_instruction_count: Option<u64>,
+    baseline_elapsed_ns: u128,
+    exec_elapsed_ns: u128,
     instruction_count_consumes_by_other_estimated: f64,
+    gas_used: u64,
+    result: String,
+    opcode_usage: String,
+}
+
+/// A case result with the columns of every kind, as stored in the results database and in csv
+/// outputs of sweeps mixing kinds.
+#[derive(Serialize)]
+pub struct CaseRow<'a> {
+    pub name: &'a str,
+    pub seed: Option<u64>,
+    #[serde(serialize_with = "serialize_kind")]
+    pub kind: TestCaseKind,
+    pub repetition: usize,
+    pub input_size: usize,
+    /// `None` when the backend could not count instructions.
+    pub baseline_instruction_count: Option<u64>,
+    pub exec_instruction_count: Option<u64>,
+    pub baseline_elapsed_ns: u128,
+    pub exec_elapsed_ns: u128,
+    /// Only estimated for mixed cases.
+    pub instruction_count_consumes_by_other_estimated: Option<f64>,
+    /// Gas spent by the measured execution.
+    pub gas_used: u64,
+    /// The instruction result the execution ended with.
+    pub result: String,
+    pub opcode_usage: String,
 }
 
-pub fn run_test(name: OpCodeOrPrecompile, mut tc: TestCase) -> TestRunResult {
+/// Measures the case with the backend, refusing measurements whose result differs from the one
+/// of executing the case on the host or that fail the sanity checks. The native backend executes
+/// on the host itself, its result is not compared.
+pub fn run_test<'a>(
+    backend: Backend,
+    luts: &'a LutRegistry,
+    name: OpCodeOrPrecompile,
+    mut tc: TestCase,
+) -> Result<TestRunResult<'a>, CaseError> {
     let kind = tc.kind();
+    let seed = tc.seed();
     let repetition = tc.repetition();
     let input_size = tc.input_size();
+    let expected_precompile_call = tc.expected_precompile_call();
 
     let bytecode_len = tc.interpreter().bytecode.len();
     let mut target_bytecode = mem::replace(
@@ -76,181 +169,435 @@ pub fn run_test(name: OpCodeOrPrecompile, mut tc: TestCase) -> TestRunResult {
         ExtBytecode::new(Bytecode::new_legacy([0u8].repeat(bytecode_len).into())),
     );
 
-    let (_, baseline_report) = {
-        let mut stdin = SP1Stdin::new();
-        stdin.write(&tc.spec_id());
-        stdin.write(&tc.interpreter());
-        stdin.write(&tc.context_builder());
-        CLIENT.execute(GUEST_ELF, &stdin).run().unwrap()
-    };
+    let baseline = backend
+        .execute(tc.spec_id(), tc.interpreter(), tc.context_builder())
+        .map_err(CaseError::Execution)?;
 
     mem::swap(&mut tc.interpreter_mut().bytecode, &mut target_bytecode);
-    let (_, exec_report) = {
-        let mut stdin = SP1Stdin::new();
-        stdin.write(&tc.spec_id());
-        stdin.write(&tc.interpreter());
-        stdin.write(&tc.context_builder());
-        CLIENT.execute(GUEST_ELF, &stdin).run().unwrap()
-    };
-
-    // let interpreter_result: InterpreterResult = output.read();
-
-    let opcodes_usage = tc.count_opcodes();
+    let exec = backend
+        .execute(tc.spec_id(), tc.interpreter(), tc.context_builder())
+        .map_err(CaseError::Execution)?;
+    let interpreter_result = exec.result.clone().ok_or_else(|| {
+        CaseError::Execution("the guest did not commit an interpreter result".to_string())
+    })?;
+
+    let (host_result, trace) = tc.trace();
+    if backend != Backend::Native && interpreter_result != host_result {
+        return Err(CaseError::Mismatch(ResultMismatch {
+            guest: interpreter_result,
+            host: host_result,
+        }));
+    }
+    let opcodes_usage = trace.usage;
 
-    TestRunResult {
+    let result = TestRunResult {
         name,
+        luts,
         kind,
+        seed,
         repetition,
         input_size,
 
-        baseline_report,
-        exec_report,
-        // interpreter_result,
+        baseline,
+        exec,
+        interpreter_result,
         opcodes_usage,
-    }
+        expected_precompile_call,
+    };
+    result.sanity_check().map_err(CaseError::SanityCheck)?;
+    Ok(result)
 }
 
 pub fn measure_jumpdest_cost(bytecode: &[u8]) -> DynamicSimpleCaseResult<'static> {
+    let start = Instant::now();
     let (_, baseline_report) = {
         let mut stdin = SP1Stdin::new();
         stdin.write(&true);
         stdin.write(&bytecode);
         CLIENT.execute(JUMPDEST_GUEST_ELF, &stdin).run().unwrap()
     };
+    let baseline_elapsed = start.elapsed();
 
+    let start = Instant::now();
     let (_, exec_report) = {
         let mut stdin = SP1Stdin::new();
         stdin.write(&false);
         stdin.write(&bytecode);
         CLIENT.execute(JUMPDEST_GUEST_ELF, &stdin).run().unwrap()
     };
+    let exec_elapsed = start.elapsed();
 
     DynamicSimpleCaseResult {
         name: "jumpdest",
+        // the bytecode comes from the scanned contracts, not from a seed
+        seed: None,
         repetition: 1,
         input_size: bytecode.len(),
         baseline_instruction_count: baseline_report.total_instruction_count(),
         exec_instruction_count: exec_report.total_instruction_count(),
+        baseline_elapsed_ns: baseline_elapsed.as_nanos(),
+        exec_elapsed_ns: exec_elapsed.as_nanos(),
+        // the analysis does not execute any opcode
+        gas_used: 0,
+        result: String::new(),
+        opcode_usage: String::new(),
+    }
+}
+
+/// Measures a transaction in the transaction guest, along with its zero-code baseline.
+pub fn measure_transaction(fixture: &TransactionFixture) -> (Measurement, Measurement) {
+    let measure = |fixture: &TransactionFixture| {
+        let mut stdin = SP1Stdin::new();
+        stdin.write(&fixture.spec);
+        stdin.write(&fixture.context_builder());
+
+        let start = Instant::now();
+        let (_, report) = CLIENT.execute(TRANSACTION_GUEST_ELF, &stdin).run().unwrap();
+        Measurement {
+            instruction_count: Some(report.total_instruction_count()),
+            elapsed: start.elapsed(),
+            report: Some(report),
+            result: None,
+        }
+    };
+    (measure(&fixture.without_code()), measure(fixture))
+}
+
+impl CaseError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            CaseError::Execution(_) => "execution",
+            CaseError::Mismatch(_) => "mismatch",
+            CaseError::SanityCheck(_) => "sanity_check",
+            CaseError::Panic(_) => "panic",
+            CaseError::Write(_) => "write",
+        }
     }
 }
 
-impl TestRunResult {
+impl Display for CaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaseError::Execution(message)
+            | CaseError::SanityCheck(message)
+            | CaseError::Panic(message)
+            | CaseError::Write(message) => f.write_str(message),
+            CaseError::Mismatch(mismatch) => mismatch.fmt(f),
+        }
+    }
+}
+
+fn serialize_kind<S: Serializer>(kind: &TestCaseKind, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(kind.to_possible_value().unwrap().get_name())
+}
+
+impl Display for ResultMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let describe = |result: &InterpreterResult| {
+            format!(
+                "{:?} with {} gas used and output {}",
+                result.result,
+                result.gas.spent(),
+                result.output
+            )
+        };
+        write!(
+            f,
+            "guest ended {}, host ended {}",
+            describe(&self.guest),
+            describe(&self.host)
+        )
+    }
+}
+
+impl TestRunResult<'_> {
+    pub fn kind(&self) -> TestCaseKind {
+        self.kind
+    }
+
+    pub fn to_case_row(&self) -> CaseRow {
+        let instruction_count_consumes_by_other_estimated = match self.kind {
+            TestCaseKind::ConstantSimple | TestCaseKind::DynamicSimple => None,
+            TestCaseKind::ConstantMixed | TestCaseKind::DynamicMixed => {
+                Some(self.count_instruction_count_consumes_by_other_estimated())
+            }
+        };
+
+        CaseRow {
+            name: self.name.as_str(),
+            seed: self.seed,
+            kind: self.kind,
+            repetition: self.repetition,
+            input_size: self.input_size,
+            baseline_instruction_count: self.baseline.instruction_count,
+            exec_instruction_count: self.exec.instruction_count,
+            baseline_elapsed_ns: self.baseline.elapsed.as_nanos(),
+            exec_elapsed_ns: self.exec.elapsed.as_nanos(),
+            instruction_count_consumes_by_other_estimated,
+            gas_used: self.gas_used(),
+            result: self.result(),
+            opcode_usage: self.opcodes_usage.to_string(),
+        }
+    }
+
     pub fn to_constant_simple_case_result(&self) -> ConstantSimpleCaseResult {
         assert!(matches!(self.kind, TestCaseKind::ConstantSimple));
-        self.sanity_check();
-        self.sanity_check_simple();
 
         ConstantSimpleCaseResult {
             name: self.name.as_str(),
+            seed: self.seed,
             repetition: self.repetition,
-            baseline_instruction_count: self.baseline_report.total_instruction_count(),
-            exec_instruction_count: self.exec_report.total_instruction_count(),
+            baseline_instruction_count: self.baseline.instruction_count,
+            exec_instruction_count: self.exec.instruction_count,
+            baseline_elapsed_ns: self.baseline.elapsed.as_nanos(),
+            exec_elapsed_ns: self.exec.elapsed.as_nanos(),
+            gas_used: self.gas_used(),
+            result: self.result(),
+            opcode_usage: self.opcodes_usage.to_string(),
         }
     }
 
     pub fn to_constant_mixed_case_result(&self) -> ConstantMixedCaseResult {
         assert!(matches!(self.kind, TestCaseKind::ConstantMixed));
-        self.sanity_check();
-        self.sanity_check_mixed();
 
         let instruction_count_consumes_by_other_estimated =
             self.count_instruction_count_consumes_by_other_estimated();
 
         ConstantMixedCaseResult {
             name: self.name.as_str(),
+            seed: self.seed,
             repetition: self.repetition,
-            baseline_instruction_count: self.baseline_report.total_instruction_count(),
-            exec_instruction_count: self.exec_report.total_instruction_count(),
+            baseline_instruction_count: self.baseline.instruction_count,
+            exec_instruction_count: self.exec.instruction_count,
+            baseline_elapsed_ns: self.baseline.elapsed.as_nanos(),
+            exec_elapsed_ns: self.exec.elapsed.as_nanos(),
             instruction_count_consumes_by_other_estimated,
+            gas_used: self.gas_used(),
+            result: self.result(),
+            opcode_usage: self.opcodes_usage.to_string(),
         }
     }
 
     pub fn to_dynamic_simple_case_result(&self) -> DynamicSimpleCaseResult {
         assert!(matches!(self.kind, TestCaseKind::DynamicSimple));
-        self.sanity_check();
-        self.sanity_check_simple();
 
         DynamicSimpleCaseResult {
             name: self.name.as_str(),
+            seed: self.seed,
             repetition: self.repetition,
             input_size: self.input_size,
-            baseline_instruction_count: self.baseline_report.total_instruction_count(),
-            exec_instruction_count: self.exec_report.total_instruction_count(),
+            baseline_instruction_count: self.baseline.instruction_count,
+            exec_instruction_count: self.exec.instruction_count,
+            baseline_elapsed_ns: self.baseline.elapsed.as_nanos(),
+            exec_elapsed_ns: self.exec.elapsed.as_nanos(),
+            gas_used: self.gas_used(),
+            result: self.result(),
+            opcode_usage: self.opcodes_usage.to_string(),
         }
     }
 
     pub fn to_dynamic_mixed_case_result(&self) -> DynamicMixedCaseResult {
         assert!(matches!(self.kind, TestCaseKind::DynamicMixed));
-        self.sanity_check();
-        self.sanity_check_mixed();
 
         let instruction_count_consumes_by_other_estimated =
             self.count_instruction_count_consumes_by_other_estimated();
 
         DynamicMixedCaseResult {
             name: self.name.as_str(),
+            seed: self.seed,
             repetition: self.repetition,
             input_size: self.input_size,
-            baseline_instruction_count: self.baseline_report.total_instruction_count(),
-            exec_instruction_count: self.exec_report.total_instruction_count(),
+            baseline_instruction_count: self.baseline.instruction_count,
+            exec_instruction_count: self.exec.instruction_count,
+            baseline_elapsed_ns: self.baseline.elapsed.as_nanos(),
+            exec_elapsed_ns: self.exec.elapsed.as_nanos(),
             instruction_count_consumes_by_other_estimated,
+            gas_used: self.gas_used(),
+            result: self.result(),
+            opcode_usage: self.opcodes_usage.to_string(),
         }
     }
 
+    pub fn baseline(&self) -> &Measurement {
+        &self.baseline
+    }
+
+    pub fn exec(&self) -> &Measurement {
+        &self.exec
+    }
+
+    /// Instructions spent per repetition of the measured opcode, excluding the estimated cost of
+    /// the other opcodes of a mixed case. `None` if the backend could not count instructions.
+    pub fn instruction_delta_per_op(&self) -> Option<f64> {
+        let other = match self.kind {
+            TestCaseKind::ConstantSimple | TestCaseKind::DynamicSimple => 0.0,
+            TestCaseKind::ConstantMixed | TestCaseKind::DynamicMixed => {
+                self.count_instruction_count_consumes_by_other_estimated()
+            }
+        };
+        let delta =
+            self.exec.instruction_count? as f64 - self.baseline.instruction_count? as f64 - other;
+        Some(delta / self.repetition as f64)
+    }
+
+    pub fn opcodes_usage(&self) -> &OpcodeUsage {
+        &self.opcodes_usage
+    }
+
+    /// Gas spent by the measured execution, the baseline only runs STOP which is free.
+    pub fn gas_used(&self) -> u64 {
+        self.interpreter_result.gas.spent()
+    }
+
+    pub fn result(&self) -> String {
+        format!("{:?}", self.interpreter_result.result)
+    }
+
     fn count_instruction_count_consumes_by_other_estimated(&self) -> f64 {
         self.opcodes_usage
             .iter()
             .filter(|(op, _)| !self.name.matches(op) && *op != OpCode::STOP)
-            .filter_map(|(op, repetition)| {
-                OPCODE_CYCLE_LUT
-                    .get(&op)
-                    .map(|model| model.estimate_cycle_count(self.input_size) * repetition as f64)
+            .filter_map(|(op, _)| {
+                self.luts
+                    .opcode(op)
+                    .map(|model| self.opcodes_usage.estimate_cycle_count(op, model))
             })
             .sum::<f64>()
     }
 
-    fn sanity_check(&self) {
-        assert_eq!(
-            self.opcodes_usage.get(OpCode::STOP),
-            Some(1),
-            "STOP should be used exactly once in a case",
-        );
-        assert_eq!(
-            self.opcodes_usage
-                .get(self.name.as_opcode())
-                .unwrap_or_default(),
-            self.repetition,
-            "Opcode usage mismatch for {}",
-            self.name.as_str(),
-        );
+    /// Checks the measurement measures what the case is meant to.
+    fn sanity_check(&self) -> Result<(), String> {
+        let name = self.name.as_str();
+        if !self.interpreter_result.result.is_ok() {
+            return Err(format!(
+                "{name} case should succeed, ended with {:?}",
+                self.interpreter_result.result
+            ));
+        }
+        if self.opcodes_usage.get(OpCode::STOP) != Some(1) {
+            return Err("STOP should be used exactly once in a case".to_string());
+        }
+        let count = self
+            .opcodes_usage
+            .get(self.name.as_opcode())
+            .unwrap_or_default();
+        if count != self.repetition {
+            return Err(format!(
+                "{name} ran {count} times, expected {}",
+                self.repetition
+            ));
+        }
+        let (_, root) = self
+            .opcodes_usage
+            .frames()
+            .next()
+            .ok_or("a case runs at least one frame")?;
+        if root
+            .get(&self.name.as_opcode())
+            .copied()
+            .unwrap_or_default()
+            != self.repetition
+        {
+            return Err(format!("{name} should only run in the outermost frame"));
+        }
+        if let OpCodeOrPrecompile::Precompile(_) = &self.name {
+            let calls = self
+                .opcodes_usage
+                .calls(OpCode::DELEGATECALL, CallTarget::Precompile);
+            if calls != self.repetition {
+                return Err(format!(
+                    "{calls} of {} DELEGATECALLs of {name} reached the precompile",
+                    self.repetition
+                ));
+            }
+        }
+        if let Some((address, input_len)) = self.expected_precompile_call {
+            let calls = self
+                .opcodes_usage
+                .precompile_calls()
+                .map(|(address, input, calls)| (address, input.len, calls.count))
+                .collect_vec();
+            if calls != [(address, input_len, self.repetition)] {
+                return Err(format!(
+                    "{name} should call precompile {address} with {input_len} bytes of input \
+                     every repetition, called {calls:?}"
+                ));
+            }
+        }
+
+        match self.kind {
+            TestCaseKind::ConstantSimple | TestCaseKind::DynamicSimple => {
+                self.sanity_check_simple()
+            }
+            TestCaseKind::ConstantMixed | TestCaseKind::DynamicMixed => self.sanity_check_mixed(),
+        }
     }
 
-    fn sanity_check_simple(&self) {
-        assert!(
-            self.opcodes_usage
-                .iter()
-                .filter(|(op, _)| { !self.name.matches(op) && *op != OpCode::STOP })
-                .next()
-                .is_none(),
-            "simple case should only use desired the opcode",
-        );
+    fn other_opcodes(&self) -> impl Iterator<Item = OpCode> + '_ {
+        self.opcodes_usage
+            .iter()
+            .map(|(op, _)| op)
+            .filter(|op| !self.name.matches(op) && *op != OpCode::STOP)
+    }
+
+    fn sanity_check_simple(&self) -> Result<(), String> {
+        let others = self.other_opcodes().collect_vec();
+        if !others.is_empty() {
+            return Err(format!(
+                "simple case should only use the desired opcode, found {others:?}"
+            ));
+        }
+        Ok(())
     }
 
-    fn sanity_check_mixed(&self) {
-        assert!(
-            self.opcodes_usage
-                .iter()
-                .filter(|(op, _)| { !self.name.matches(op) && *op != OpCode::STOP })
-                .all(|(op, _)| OPCODE_CYCLE_LUT.contains_key(&op)),
-            "found opcode not in constant lut: {:?}",
-            self.opcodes_usage
-                .iter()
-                .filter(|(op, _)| {
-                    !self.name.matches(op)
-                        && *op != OpCode::STOP
-                        && !OPCODE_CYCLE_LUT.contains_key(&op)
-                })
-                .collect_vec()
-        );
+    fn sanity_check_mixed(&self) -> Result<(), String> {
+        let missing = self
+            .other_opcodes()
+            .filter(|op| !self.luts.contains_opcode(*op))
+            .collect_vec();
+        if !missing.is_empty() {
+            return Err(format!("found opcode not in constant lut: {missing:?}"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl<'a> TestRunResult<'a> {
+    /// A successful result measured with the given instruction counts, without running the case.
+    pub(crate) fn measured(
+        luts: &'a LutRegistry,
+        name: OpCodeOrPrecompile,
+        kind: TestCaseKind,
+        (repetition, input_size): (usize, usize),
+        (baseline, exec): (u64, u64),
+    ) -> Self {
+        use evm_guest::Bytes;
+        use revm_interpreter::{Gas, InstructionResult};
+        use std::time::Duration;
+
+        let measurement = |instruction_count| Measurement {
+            instruction_count: Some(instruction_count),
+            elapsed: Duration::ZERO,
+            report: None,
+            result: None,
+        };
+        Self {
+            name,
+            luts,
+            kind,
+            seed: Some(u64::MAX),
+            repetition,
+            input_size,
+            baseline: measurement(baseline),
+            exec: measurement(exec),
+            interpreter_result: InterpreterResult::new(
+                InstructionResult::Stop,
+                Bytes::new(),
+                Gas::new(0),
+            ),
+            opcodes_usage: OpcodeUsage::default(),
+            expected_precompile_call: None,
+        }
     }
 }
@@ -0,0 +1,197 @@
+use crate::commands::{
+    manifest::Manifest,
+    results::{ResultRecord, read_results},
+};
+use clap::Args;
+use itertools::Itertools;
+use revm_bytecode::OpCode;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::PathBuf,
+};
+use test_vector::{CycleLut, CycleModel, LutEntry, OpcodeUsage, fitting::NormalEquations};
+
+#[derive(Debug, Args)]
+pub struct SolveCommand {
+    /// Results to solve, a csv or a results database
+    #[clap(default_value = "results.csv")]
+    input: PathBuf,
+    #[clap(long, default_value = "opcode-lut.json")]
+    opcode_lut_out: PathBuf,
+    #[clap(long, default_value = "precompile-lut.json")]
+    precompile_lut_out: PathBuf,
+    #[clap(long, default_value = "residuals.csv")]
+    residuals: PathBuf,
+}
+
+/// How well the solved costs explain a single case.
+#[derive(Serialize)]
+struct Residual<'a> {
+    name: &'a str,
+    repetition: usize,
+    input_size: Option<usize>,
+    observed: f64,
+    predicted: f64,
+    residual: f64,
+    relative_residual: f64,
+}
+
+/// A cost being solved for, the slope of a dynamic case is solved separately from its constant.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
+enum Variable {
+    Cost(String),
+    Slope(String),
+}
+
+impl SolveCommand {
+    /// Solves every opcode and precompile cost jointly, each case being the equation
+    /// `exec - baseline = Σ count(op) * cost(op)` over the opcodes it executed.
+    ///
+    /// The cost of the measured opcode of a dynamic case grows with its input size, the helper
+    /// opcodes are assumed to be constant.
+    pub fn run(self) {
+        let records = read_results(&self.input)
+            .into_iter()
+            // results measured without an instruction counter have nothing to solve for
+            .filter(|record| record.instruction_delta().is_some())
+            .filter_map(|record| {
+                let usage = record.opcode_usage.as_deref()?.parse::<OpcodeUsage>();
+                match usage {
+                    Ok(usage) => Some((record, usage)),
+                    Err(e) => {
+                        eprintln!("Skipping a {} case: {e}", record.name);
+                        None
+                    }
+                }
+            })
+            .collect_vec();
+        if records.is_empty() {
+            eprintln!("No results with opcode usage and instruction counts, nothing to solve.");
+            return;
+        }
+
+        let dynamic = records
+            .iter()
+            .map(|(record, _)| (record.name.as_str(), record.input_size))
+            .unique()
+            .map(|(name, _)| name)
+            .duplicates()
+            .collect::<BTreeSet<_>>();
+
+        let rows = records
+            .iter()
+            .map(|(record, usage)| {
+                let row = equation(record, usage, dynamic.contains(record.name.as_str()));
+                (row, observed(record))
+            })
+            .collect_vec();
+        let variables = rows
+            .iter()
+            .flat_map(|(row, _)| row.keys().cloned())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect_vec();
+        let index = |variable: &Variable| variables.binary_search(variable).unwrap();
+
+        let mut equations = NormalEquations::new(variables.len());
+        for ((row, observed), (record, _)) in rows.iter().zip(&records) {
+            // scaled to per repetition, so long cases do not dominate the fit
+            let scale = 1.0 / record.repetition.max(1) as f64;
+            let row = row
+                .iter()
+                .map(|(variable, coefficient)| (index(variable), coefficient * scale))
+                .collect_vec();
+            equations.add_row(&row, observed * scale);
+        }
+        let solution = equations.solve_nonnegative();
+        let value = |variable: &Variable| {
+            variables
+                .binary_search(variable)
+                .map_or(0.0, |idx| solution[idx])
+        };
+
+        let mut writer = csv::Writer::from_path(&self.residuals).unwrap();
+        for ((row, observed), (record, _)) in rows.iter().zip(&records) {
+            let predicted = row
+                .iter()
+                .map(|(variable, coefficient)| coefficient * value(variable))
+                .sum::<f64>();
+            let residual = observed - predicted;
+            writer
+                .serialize(Residual {
+                    name: &record.name,
+                    repetition: record.repetition,
+                    input_size: record.input_size,
+                    observed: *observed,
+                    predicted,
+                    residual,
+                    relative_residual: residual / observed.abs().max(1.0),
+                })
+                .unwrap();
+        }
+        writer.flush().unwrap();
+
+        let source_run = Manifest::read_id(&self.input);
+        let mut opcode_lut = CycleLut::new();
+        let mut precompile_lut = CycleLut::new();
+        for variable in &variables {
+            let Variable::Cost(name) = variable else {
+                continue;
+            };
+            let cost = value(variable);
+            let model = if dynamic.contains(name.as_str()) {
+                CycleModel::Linear {
+                    slope: value(&Variable::Slope(name.clone())),
+                    intercept: cost,
+                }
+            } else {
+                CycleModel::Constant { cycles: cost }
+            };
+            println!("{name}: {model:?}");
+            let entry = LutEntry {
+                model,
+                stats: None,
+                source_run: source_run.clone(),
+            };
+            if OpCode::parse(name).is_some() {
+                opcode_lut.entries.insert(name.clone(), entry);
+            } else {
+                precompile_lut.entries.insert(name.clone(), entry);
+            }
+        }
+
+        for (path, lut) in [
+            (&self.opcode_lut_out, &opcode_lut),
+            (&self.precompile_lut_out, &precompile_lut),
+        ] {
+            fs::write(path, serde_json::to_string_pretty(lut).unwrap())
+                .expect("failed to write LUT");
+        }
+    }
+}
+
+/// The coefficients of the equation of a case, STOP is executed by the baseline as well.
+fn equation(record: &ResultRecord, usage: &OpcodeUsage, dynamic: bool) -> BTreeMap<Variable, f64> {
+    let mut row = BTreeMap::new();
+    for (op, count) in usage.iter().filter(|(op, _)| *op != OpCode::STOP) {
+        *row.entry(Variable::Cost(op.as_str().to_string()))
+            .or_default() += count as f64;
+    }
+    // a precompile runs once per repetition, behind the call opcode
+    if OpCode::parse(&record.name).is_none() {
+        *row.entry(Variable::Cost(record.name.clone())).or_default() += record.repetition as f64;
+    }
+    if dynamic {
+        *row.entry(Variable::Slope(record.name.clone())).or_default() +=
+            (record.repetition * record.input_size.unwrap_or_default()) as f64;
+    }
+    row
+}
+
+fn observed(record: &ResultRecord) -> f64 {
+    record
+        .instruction_delta()
+        .expect("results without instruction counts are skipped")
+}
@@ -0,0 +1,218 @@
+use crate::commands::{
+    estimate::read_fixture, failures::panic_message, runner::measure_transaction,
+};
+use clap::Args;
+use itertools::Itertools;
+use rayon::iter::{Either, IntoParallelRefIterator, ParallelIterator};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    fs,
+    panic::{AssertUnwindSafe, catch_unwind},
+    path::{Path, PathBuf},
+};
+use test_vector::{
+    LutRegistry, OpCodeOrPrecompile,
+    estimate::CycleEstimate,
+    fitting::{median, quantile},
+};
+
+#[derive(Debug, Args)]
+pub struct ValidateCommand {
+    /// Transaction fixtures, json files or directories of them
+    #[clap(required = true)]
+    fixtures: Vec<PathBuf>,
+    #[clap(long, default_value = "validation.csv")]
+    out: PathBuf,
+    /// Number of opcodes and precompiles flagged as contributing most to the residual
+    #[clap(long, default_value_t = 10)]
+    top: usize,
+    /// Opcode cycle LUT used instead of the embedded one
+    #[clap(long, env = "OPCODE_CYCLE_LUT")]
+    opcode_lut: Option<PathBuf>,
+    /// Precompile cycle LUT used instead of the embedded one
+    #[clap(long, env = "PRECOMPILE_CYCLE_LUT")]
+    precompile_lut: Option<PathBuf>,
+}
+
+#[derive(Debug, Serialize)]
+struct Validation {
+    name: String,
+    success: bool,
+    gas_used: u64,
+    baseline_instruction_count: u64,
+    exec_instruction_count: u64,
+    /// Cycles of the opcodes, the execution minus the zero-code baseline.
+    measured: f64,
+    estimated: f64,
+    residual: f64,
+    relative_residual: f64,
+    /// Executed opcodes and called precompiles without a cycle model.
+    missing: String,
+}
+
+/// A fixture that could not be validated.
+#[derive(Debug, Serialize)]
+struct ValidationFailure {
+    name: String,
+    message: String,
+}
+
+impl ValidateCommand {
+    /// Compares the estimate of every transaction with the cycles measured in the guest.
+    pub fn run(self) {
+        let luts = LutRegistry::load(self.opcode_lut.as_deref(), self.precompile_lut.as_deref())
+            .unwrap_or_else(|e| panic!("failed to load cycle LUTs: {e}"));
+        let paths = self
+            .fixtures
+            .iter()
+            .flat_map(|path| fixture_paths(path))
+            .collect_vec();
+
+        let (results, failures): (Vec<_>, Vec<_>) = paths
+            .par_iter()
+            .map(|path| {
+                catch_unwind(AssertUnwindSafe(|| validate(&luts, path)))
+                    .unwrap_or_else(|payload| Err(panic_message(&*payload)))
+                    .map_err(|message| ValidationFailure {
+                        name: fixture_name(path),
+                        message,
+                    })
+            })
+            .partition_map(|result| match result {
+                Ok(validated) => Either::Left(validated),
+                Err(failure) => Either::Right(failure),
+            });
+        if !failures.is_empty() {
+            let mut path = self.out.as_os_str().to_owned();
+            path.push(".failures.csv");
+            let mut writer = csv::Writer::from_path(&path).unwrap();
+            for failure in &failures {
+                eprintln!("{} failed: {}", failure.name, failure.message);
+                writer.serialize(failure).unwrap();
+            }
+            writer.flush().unwrap();
+            eprintln!(
+                "{} fixtures failed and were not validated, see {}.",
+                failures.len(),
+                Path::new(&path).display()
+            );
+        }
+        if results.is_empty() {
+            eprintln!("No fixtures to validate.");
+            return;
+        }
+
+        let mut writer = csv::Writer::from_path(&self.out).unwrap();
+        for (validation, _) in &results {
+            writer.serialize(validation).unwrap();
+        }
+        writer.flush().unwrap();
+
+        let relative = results
+            .iter()
+            .map(|(validation, _)| validation.relative_residual)
+            .collect_vec();
+        let absolute = relative.iter().map(|r| r.abs()).collect_vec();
+        println!("Relative residual over {} transactions:", relative.len());
+        println!(
+            "  p5 {:+.2}%, median {:+.2}%, p95 {:+.2}%",
+            quantile(&relative, 0.05) * 100.0,
+            median(&relative) * 100.0,
+            quantile(&relative, 0.95) * 100.0
+        );
+        println!(
+            "  mean absolute {:.2}%, max absolute {:.2}%",
+            absolute.iter().sum::<f64>() / absolute.len() as f64 * 100.0,
+            absolute.iter().copied().fold(0.0, f64::max) * 100.0
+        );
+
+        println!("Opcodes and precompiles contributing most to the residual:");
+        for (name, residual) in attribute_residuals(&results)
+            .into_iter()
+            .sorted_by(|(_, a), (_, b)| b.abs().total_cmp(&a.abs()))
+            .take(self.top)
+        {
+            println!("  {:<16} {residual:+.0}", name.as_str());
+        }
+    }
+}
+
+/// Estimates and measures the transaction of a fixture.
+fn validate(luts: &LutRegistry, path: &Path) -> Result<(Validation, CycleEstimate), String> {
+    let fixture = read_fixture(path);
+    let (result, trace) = fixture.trace();
+    let result = result.map_err(|e| format!("transaction is invalid: {e:?}"))?;
+    let estimate = luts.estimate(&trace);
+    let (baseline, exec) = measure_transaction(&fixture);
+    let counted = "the SP1 executor counts instructions";
+    let baseline_instruction_count = baseline.instruction_count.expect(counted);
+    let exec_instruction_count = exec.instruction_count.expect(counted);
+
+    let measured = exec_instruction_count as f64 - baseline_instruction_count as f64;
+    let residual = measured - estimate.total;
+    let validation = Validation {
+        name: fixture_name(path),
+        success: result.is_success(),
+        gas_used: result.gas_used(),
+        baseline_instruction_count,
+        exec_instruction_count,
+        measured,
+        estimated: estimate.total,
+        residual,
+        relative_residual: residual / measured.abs().max(1.0),
+        missing: estimate.missing.iter().map(|name| name.as_str()).join(";"),
+    };
+    Ok((validation, estimate))
+}
+
+fn fixture_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Splits the residual of every transaction over its opcodes and precompiles in proportion to
+/// their estimated cycles, summed over the transactions.
+///
+/// Transactions running anything without a cycle model are skipped, their residual holds the
+/// cycles of what is missing and would be spread over unrelated opcodes.
+fn attribute_residuals(
+    results: &[(Validation, CycleEstimate)],
+) -> BTreeMap<OpCodeOrPrecompile, f64> {
+    let mut attributed = BTreeMap::new();
+    for (validation, estimate) in results {
+        if estimate.total <= 0.0 || !estimate.missing.is_empty() {
+            continue;
+        }
+        let opcodes = estimate
+            .opcodes
+            .iter()
+            .map(|(op, opcode)| (OpCodeOrPrecompile::OpCode(*op), opcode.cycles));
+        let precompiles = estimate.precompiles.iter().map(|(name, precompile)| {
+            (
+                OpCodeOrPrecompile::Precompile(name.clone()),
+                precompile.cycles,
+            )
+        });
+        for (name, cycles) in opcodes.chain(precompiles) {
+            *attributed.entry(name).or_default() += validation.residual * cycles / estimate.total;
+        }
+    }
+    attributed
+}
+
+pub(super) fn fixture_paths(path: &Path) -> Vec<PathBuf> {
+    if !path.is_dir() {
+        return vec![path.to_path_buf()];
+    }
+    fs::read_dir(path)
+        .unwrap_or_else(|e| panic!("failed to read {}: {e}", path.display()))
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "json")
+        })
+        .sorted()
+        .collect()
+}
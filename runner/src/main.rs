@@ -2,7 +2,9 @@ use clap::Parser;
 
 const GUEST_ELF: &[u8] = include_bytes!("../elf/evm-guest");
 const JUMPDEST_GUEST_ELF: &[u8] = include_bytes!("../elf/jumpdest-analyze-guest");
+const TRANSACTION_GUEST_ELF: &[u8] = include_bytes!("../elf/transaction-guest");
 
+mod backend;
 mod commands;
 
 #[derive(Parser)]
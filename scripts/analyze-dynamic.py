@@ -1,4 +1,5 @@
 import sys
+import json
 
 import seaborn as sns
 import matplotlib.pyplot as plt
@@ -32,6 +33,10 @@ slope = model.coef_[0]
 intercept = model.intercept_
 r2 = model.score(x, y)
 print(f"y = {slope}x + {intercept}")
+entry = linear_entry(
+    x, y, df["input_size"], df["instruction_delta_per_op"], slope, intercept, r2, load_manifest_id(file)
+)
+print(json.dumps({name: entry}, indent=2))
 
 plt.figure(figsize=(10, 6))
 
@@ -20,5 +20,11 @@ df_grouped = df.groupby("name", group_keys=True)
 agged = df_grouped["instruction_delta_per_op"].agg(agg_mode)
 print(agged)
 
+manifest_id = load_manifest_id(file)
+entries = {
+    name: constant_entry(samples, float(agged[name]), manifest_id)
+    for name, samples in df_grouped["instruction_delta_per_op"]
+}
+
 with open(out, "w") as f:
-    json.dump(agged.to_dict(), f, indent=2)
+    json.dump(make_lut(entries), f, indent=2)
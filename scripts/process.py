@@ -1,6 +1,66 @@
+import json
+import os
+
 import numpy as np
 import pandas as pd
 
+def load_manifest_id(file: str):
+    path = file + ".manifest.json"
+    if not os.path.exists(path):
+        return None
+    with open(path) as f:
+        return json.load(f)["id"]
+
+LUT_VERSION = 1
+Z_95 = 1.959963984540054
+
+def make_lut(entries: dict) -> dict:
+    return {"version": LUT_VERSION, "entries": entries}
+
+def stddev_around(values, center) -> float:
+    values = np.asarray(values, dtype=float)
+    if len(values) < 2:
+        return 0.0
+    return float(np.sqrt(((values - center) ** 2).sum() / (len(values) - 1)))
+
+def constant_entry(samples: pd.Series, cycles: float, manifest_id) -> dict:
+    n = len(samples)
+    stddev = stddev_around(samples, cycles)
+    half = Z_95 * stddev / np.sqrt(n)
+    entry = {
+        "kind": "constant",
+        "cycles": cycles,
+        "stats": {"n": n, "r2": None, "stddev": stddev, "ci": [cycles - half, cycles + half]},
+    }
+    if manifest_id is not None:
+        entry["source_run"] = manifest_id
+    return entry
+
+def linear_entry(x, y, samples_x, samples_y, slope, intercept, r2, manifest_id) -> dict:
+    x = np.asarray(x, dtype=float).ravel()
+    y = np.asarray(y, dtype=float)
+    if len(x) > 2:
+        ss_res = ((y - (slope * x + intercept)) ** 2).sum()
+        sxx = ((x - x.mean()) ** 2).sum()
+        slope_stderr = float(np.sqrt(ss_res / (len(x) - 2) / sxx))
+    else:
+        slope_stderr = 0.0
+    residuals = np.asarray(samples_y, dtype=float) - (slope * np.asarray(samples_x, dtype=float) + intercept)
+    entry = {
+        "kind": "linear",
+        "slope": slope,
+        "intercept": intercept,
+        "stats": {
+            "n": len(residuals),
+            "r2": r2,
+            "stddev": stddev_around(residuals, 0.0),
+            "ci": [slope - Z_95 * slope_stderr, slope + Z_95 * slope_stderr],
+        },
+    }
+    if manifest_id is not None:
+        entry["source_run"] = manifest_id
+    return entry
+
 def process_simple(df: pd.DataFrame) -> pd.DataFrame:
     delta = df["exec_instruction_count"] - df["baseline_instruction_count"]
     if "instruction_count_consumes_by_other_estimated" in df.columns:
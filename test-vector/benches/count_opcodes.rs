@@ -0,0 +1,212 @@
+//! Throughput of counting opcodes on the host, run with `cargo bench -p test-vector`.
+//!
+//! Every test vector is counted with the per-thread cell counter of the crate and with the mutex
+//! based counter it replaced, kept below. The mutex counter only counts opcodes and input sizes,
+//! the cell counter also credits them to frames and classifies call targets.
+
+use evm_guest::OpCode;
+use std::{hint::black_box, time::Instant};
+use test_vector::{OPCODE_TEST_VECTORS, TestCase};
+
+const ITERATIONS: usize = 20;
+
+/// The mutex based counter, as it was before counting moved to per-thread cells.
+mod mutex_counter {
+    use evm_guest::{
+        interpreter::{instructions::instruction_table, interpreter::EthInterpreter},
+        *,
+    };
+    use std::{
+        collections::BTreeMap,
+        sync::{LazyLock, Mutex},
+    };
+
+    static MAINNET: InstructionTableT = instruction_table::<EthInterpreter, ContextT>();
+    static TABLE: LazyLock<InstructionTableT> = LazyLock::new(|| {
+        macro_rules! row {
+            ($hi:literal) => {
+                [
+                    counted::<{ $hi * 16 }>,
+                    counted::<{ $hi * 16 + 1 }>,
+                    counted::<{ $hi * 16 + 2 }>,
+                    counted::<{ $hi * 16 + 3 }>,
+                    counted::<{ $hi * 16 + 4 }>,
+                    counted::<{ $hi * 16 + 5 }>,
+                    counted::<{ $hi * 16 + 6 }>,
+                    counted::<{ $hi * 16 + 7 }>,
+                    counted::<{ $hi * 16 + 8 }>,
+                    counted::<{ $hi * 16 + 9 }>,
+                    counted::<{ $hi * 16 + 10 }>,
+                    counted::<{ $hi * 16 + 11 }>,
+                    counted::<{ $hi * 16 + 12 }>,
+                    counted::<{ $hi * 16 + 13 }>,
+                    counted::<{ $hi * 16 + 14 }>,
+                    counted::<{ $hi * 16 + 15 }>,
+                ]
+            };
+        }
+        let rows: [[InstructionT; 16]; 16] = [
+            row!(0),
+            row!(1),
+            row!(2),
+            row!(3),
+            row!(4),
+            row!(5),
+            row!(6),
+            row!(7),
+            row!(8),
+            row!(9),
+            row!(10),
+            row!(11),
+            row!(12),
+            row!(13),
+            row!(14),
+            row!(15),
+        ];
+        rows.as_flattened()
+            .try_into()
+            .expect("16 rows of 16 instructions")
+    });
+
+    thread_local! {
+        static COUNTER: InstructionCounter = const { InstructionCounter::new() };
+    }
+
+    struct InstructionCounter {
+        reentrant_lock: Mutex<()>,
+        count: Mutex<[usize; 256]>,
+        input_sizes: Mutex<Vec<(u8, usize)>>,
+    }
+
+    impl InstructionCounter {
+        const fn new() -> Self {
+            Self {
+                reentrant_lock: Mutex::new(()),
+                count: Mutex::new([0; 256]),
+                input_sizes: Mutex::new(Vec::new()),
+            }
+        }
+
+        fn count(&self, op: u8, input_size: Option<usize>) {
+            let mut count = self.count.lock().unwrap();
+            count[op as usize] += 1;
+            if let Some(input_size) = input_size {
+                self.input_sizes.lock().unwrap().push((op, input_size));
+            }
+        }
+    }
+
+    fn counted<const OP: u8>(interpreter: &mut InterpreterT, host: &mut ContextT) {
+        COUNTER.with(|c| c.count(OP, input_size(OP, &interpreter.stack)));
+        MAINNET[OP as usize](interpreter, host)
+    }
+
+    fn input_size(op: u8, stack: &Stack) -> Option<usize> {
+        let size_at = |n| stack.peek(n).ok().map(|size| size.saturating_to::<usize>());
+        match OpCode::new(op)? {
+            OpCode::EXP => stack
+                .peek(1)
+                .ok()
+                .map(|exponent| exponent.bit_len().div_ceil(8)),
+            OpCode::KECCAK256
+            | OpCode::LOG0
+            | OpCode::LOG1
+            | OpCode::LOG2
+            | OpCode::LOG3
+            | OpCode::LOG4 => size_at(1),
+            OpCode::CALLDATACOPY | OpCode::CODECOPY | OpCode::RETURNDATACOPY | OpCode::MCOPY => {
+                size_at(2)
+            }
+            OpCode::EXTCODECOPY => size_at(3),
+            OpCode::CALL | OpCode::CALLCODE => size_at(4),
+            OpCode::DELEGATECALL | OpCode::STATICCALL => size_at(3),
+            _ => None,
+        }
+    }
+
+    /// Executes the interpreter with the mutex counter, returning the number of opcodes it ran.
+    pub fn count_opcodes(
+        spec_id: SpecId,
+        interpreter: InterpreterT,
+        context_builder: &ContextBuilder,
+    ) -> usize {
+        COUNTER.with(|counter| {
+            let _guard = counter.reentrant_lock.lock().unwrap();
+            *counter.count.lock().unwrap() = [0; 256];
+            counter.input_sizes.lock().unwrap().clear();
+
+            execute(
+                spec_id,
+                interpreter,
+                context_builder,
+                EthInstructionsT::new(*TABLE),
+            );
+
+            let mut input_sizes: BTreeMap<u8, BTreeMap<usize, usize>> = BTreeMap::new();
+            for (op, input_size) in counter.input_sizes.lock().unwrap().iter() {
+                *input_sizes
+                    .entry(*op)
+                    .or_default()
+                    .entry(*input_size)
+                    .or_default() += 1;
+            }
+            std::hint::black_box(input_sizes);
+            counter.count.lock().unwrap().iter().sum()
+        })
+    }
+}
+
+fn report(name: &str, counter: &str, executed: usize, start: Instant) {
+    let elapsed = start.elapsed();
+    println!(
+        "{name:<12} {counter:<6} {executed:>10} opcodes in {elapsed:>10.2?}, {:>8.2} Mop/s",
+        executed as f64 / elapsed.as_secs_f64() / 1e6
+    );
+}
+
+fn bench(name: &str, build: impl Fn() -> TestCase) {
+    let cases = (0..ITERATIONS).map(|_| build()).collect::<Vec<_>>();
+    let interpreters = cases
+        .iter()
+        .map(|case| case.interpreter().clone())
+        .collect::<Vec<_>>();
+
+    let start = Instant::now();
+    let executed = cases
+        .iter()
+        .zip(interpreters)
+        .map(|(case, interpreter)| {
+            black_box(mutex_counter::count_opcodes(
+                case.spec_id(),
+                interpreter,
+                case.context_builder(),
+            ))
+        })
+        .sum::<usize>();
+    report(name, "mutex", executed, start);
+
+    let start = Instant::now();
+    let executed = cases
+        .into_iter()
+        .map(|case| {
+            black_box(case.count_opcodes())
+                .iter()
+                .map(|(_, count)| count)
+                .sum::<usize>()
+        })
+        .sum::<usize>();
+    report(name, "cells", executed, start);
+}
+
+fn main() {
+    for op in [OpCode::ADD, OpCode::MSTORE, OpCode::KECCAK256] {
+        let builder = &OPCODE_TEST_VECTORS[&op];
+        let (repetition, input_size) = builder
+            .params()
+            .max()
+            .expect("test vector without parameters");
+        bench(op.as_str(), || {
+            builder.build(repetition, input_size, Some(42))
+        });
+    }
+}
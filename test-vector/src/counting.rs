@@ -1,73 +1,452 @@
-use evm_guest::*;
-use std::{collections::BTreeMap, sync::Mutex};
+use crate::{CycleModel, CycleParams, filler::precompile::modexp_lengths};
+use evm_guest::{primitives::KECCAK_EMPTY, *};
+use revm_precompile::{PrecompileSpecId, Precompiles};
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeMap,
+    fmt::{self, Display},
+    str::FromStr,
+};
 
 thread_local! {
-    pub(crate) static INSTRUCTION_COUNTER: InstructionCounter = InstructionCounter::new();
+    pub(crate) static INSTRUCTION_COUNTER: InstructionCounter = const { InstructionCounter::new() };
     pub(crate) static INSTRUCTION_TABLE_WITH_COUNTING: InstructionTableT = instruction_table();
 }
 
+/// Counts the opcodes executed on the current thread, without any locking as it is never shared.
 pub(crate) struct InstructionCounter {
-    reentrant_lock: Mutex<()>,
-    count: Mutex<[usize; 256]>,
+    in_use: Cell<bool>,
+    count: [Cell<usize>; 256],
+    /// (opcode, input size) -> count, only for the dynamic opcodes
+    input_sizes: RefCell<BTreeMap<(u8, usize), usize>>,
+    /// The frame of the last counted opcode, frames change far less often than opcodes so the
+    /// counts are only credited to a frame when it changes.
+    current_frame: Cell<Option<Frame>>,
+    /// `count` when the current frame was entered.
+    frame_start: RefCell<[usize; 256]>,
+    frames: RefCell<BTreeMap<Frame, [usize; 256]>>,
+    calls: RefCell<BTreeMap<(OpCode, CallTarget), usize>>,
+    /// The precompiles of the spec of the execution, looked up on its first call opcode.
+    precompiles: Cell<Option<&'static Precompiles>>,
+    precompile_calls: RefCell<BTreeMap<(Address, PrecompileInput), PrecompileCalls>>,
 }
 
+/// Exclusive use of the counter of the thread, released on drop.
 pub(crate) struct InstructionCounterGuard<'a> {
-    _guard: std::sync::MutexGuard<'a, ()>,
-    counter: &'a Mutex<[usize; 256]>,
+    counter: &'a InstructionCounter,
 }
 
+/// A call frame opcodes ran in.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Frame {
+    /// The journal depth of the frame.
+    pub depth: usize,
+    pub address: Address,
+}
+
+/// What a call opcode called into.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum CallTarget {
+    Precompile,
+    Contract,
+    /// An account without code, the call returns without running anything.
+    Empty,
+}
+
+/// The precompiles of the spec, recording every call into them in the counter of the thread.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct CountingPrecompiles {
+    inner: EthPrecompiles,
+}
+
+/// The input of a precompile call, as far as the estimate of its cycles depends on it.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct PrecompileInput {
+    pub len: usize,
+    /// The base, exponent and modulus lengths of a modexp call, read from the head of its input.
+    pub modexp_lengths: Option<[usize; 3]>,
+}
+
+/// The calls into a precompile with the same input.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct PrecompileCalls {
+    pub count: usize,
+    pub gas_used: u64,
+}
+
+/// The opcodes an execution ran.
+///
+/// Only the counts and input sizes are part of the string form, the frames, call targets and
+/// precompile calls are only available on a freshly counted usage.
 #[derive(Default, Debug, Clone)]
-pub struct OpcodeUsage(BTreeMap<OpCode, usize>);
+pub struct OpcodeUsage {
+    counts: BTreeMap<OpCode, usize>,
+    /// opcode -> input size -> count, only for the dynamic opcodes
+    input_sizes: BTreeMap<OpCode, BTreeMap<usize, usize>>,
+    frames: BTreeMap<Frame, BTreeMap<OpCode, usize>>,
+    calls: BTreeMap<(OpCode, CallTarget), usize>,
+    /// (precompile, input) -> calls
+    precompile_calls: BTreeMap<(Address, PrecompileInput), PrecompileCalls>,
+}
 
 impl InstructionCounter {
     const fn new() -> Self {
         Self {
-            reentrant_lock: Mutex::new(()),
-            count: Mutex::new([0; 256]),
+            in_use: Cell::new(false),
+            count: [const { Cell::new(0) }; 256],
+            input_sizes: RefCell::new(BTreeMap::new()),
+            current_frame: Cell::new(None),
+            frame_start: RefCell::new([0; 256]),
+            frames: RefCell::new(BTreeMap::new()),
+            calls: RefCell::new(BTreeMap::new()),
+            precompiles: Cell::new(None),
+            precompile_calls: RefCell::new(BTreeMap::new()),
         }
     }
 
+    /// Takes the counter, panics if it is already taken by an execution further up the stack.
     pub(crate) fn lock(&self) -> InstructionCounterGuard {
-        let _guard = self.reentrant_lock.lock().unwrap();
-        InstructionCounterGuard {
-            _guard,
-            counter: &self.count,
+        assert!(
+            !self.in_use.replace(true),
+            "instruction counter is already in use on this thread"
+        );
+        InstructionCounterGuard { counter: self }
+    }
+
+    #[inline]
+    pub(crate) fn count(&self, op: u8, interpreter: &InterpreterT, host: &ContextT) {
+        let frame = Frame {
+            depth: host.journaled_state.depth,
+            address: interpreter.input.target_address,
+        };
+        if self.current_frame.get() != Some(frame) {
+            self.switch_frame(Some(frame));
+        }
+
+        let count = &self.count[op as usize];
+        count.set(count.get() + 1);
+        if let Some(input_size) = input_size(op, &interpreter.stack) {
+            *self
+                .input_sizes
+                .borrow_mut()
+                .entry((op, input_size))
+                .or_default() += 1;
+        }
+        if let Some(target) = self.call_target(op, &interpreter.stack, host) {
+            let op = OpCode::new(op).expect("call opcodes are valid");
+            *self.calls.borrow_mut().entry((op, target)).or_default() += 1;
+        }
+    }
+
+    /// Credits the opcodes counted since the current frame was entered to it, and enters `frame`.
+    #[cold]
+    fn switch_frame(&self, frame: Option<Frame>) {
+        let mut frame_start = self.frame_start.borrow_mut();
+        if let Some(current) = self.current_frame.replace(frame) {
+            let mut frames = self.frames.borrow_mut();
+            let counts = frames.entry(current).or_insert([0; 256]);
+            for ((count, start), total) in
+                counts.iter_mut().zip(frame_start.iter()).zip(&self.count)
+            {
+                *count += total.get() - start;
+            }
+        }
+        for (start, total) in frame_start.iter_mut().zip(&self.count) {
+            *start = total.get();
+        }
+    }
+
+    /// Classifies the target of a call opcode, read from the stack before the call executes.
+    fn call_target(&self, op: u8, stack: &Stack, host: &ContextT) -> Option<CallTarget> {
+        if !matches!(
+            OpCode::new(op)?,
+            OpCode::CALL | OpCode::CALLCODE | OpCode::DELEGATECALL | OpCode::STATICCALL
+        ) {
+            return None;
         }
+        let address = Address::from_word(stack.peek(1).ok()?.into());
+        let precompiles = self.precompiles.get().unwrap_or_else(|| {
+            let precompiles = Precompiles::new(PrecompileSpecId::from_spec_id(host.cfg.spec));
+            self.precompiles.set(Some(precompiles));
+            precompiles
+        });
+        if precompiles.contains(&address) {
+            return Some(CallTarget::Precompile);
+        }
+        let info = host
+            .journaled_state
+            .state
+            .get(&address)
+            .map(|account| &account.info)
+            .or_else(|| {
+                host.journaled_state
+                    .database
+                    .cache
+                    .accounts
+                    .get(&address)
+                    .map(|account| &account.info)
+            });
+        let has_code = info.is_some_and(|info| match &info.code {
+            Some(code) => !code.is_empty(),
+            None => info.code_hash != KECCAK_EMPTY && info.code_hash != B256::ZERO,
+        });
+        Some(if has_code {
+            CallTarget::Contract
+        } else {
+            CallTarget::Empty
+        })
     }
 
-    pub(crate) fn count(&self, op: u8) {
-        let mut count = self.count.lock().unwrap();
-        count[op as usize] += 1;
+    fn count_precompile(&self, address: Address, input: PrecompileInput, gas_used: u64) {
+        let mut precompile_calls = self.precompile_calls.borrow_mut();
+        let calls = precompile_calls.entry((address, input)).or_default();
+        calls.count += 1;
+        calls.gas_used += gas_used;
     }
 }
 
 impl InstructionCounterGuard<'_> {
     pub(crate) fn read(&self) -> OpcodeUsage {
-        let count = self.counter.lock().unwrap();
-        let map = count
-            .into_iter()
+        self.counter.switch_frame(None);
+        let counts = self
+            .counter
+            .count
+            .iter()
+            .map(Cell::get)
             .enumerate()
             .filter(|(_, v)| *v > 0)
             .filter_map(|(i, v)| OpCode::new(i as u8).map(|op| (op, v)))
             .collect();
-        OpcodeUsage(map)
+        let mut input_sizes: BTreeMap<OpCode, BTreeMap<usize, usize>> = BTreeMap::new();
+        for ((op, input_size), count) in self.counter.input_sizes.borrow().iter() {
+            if let Some(op) = OpCode::new(*op) {
+                input_sizes
+                    .entry(op)
+                    .or_default()
+                    .insert(*input_size, *count);
+            }
+        }
+        let frames = self
+            .counter
+            .frames
+            .borrow()
+            .iter()
+            .map(|(frame, count)| {
+                let count = count
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, v)| **v > 0)
+                    .filter_map(|(i, v)| OpCode::new(i as u8).map(|op| (op, *v)))
+                    .collect();
+                (*frame, count)
+            })
+            .collect();
+        OpcodeUsage {
+            counts,
+            input_sizes,
+            frames,
+            calls: self.counter.calls.borrow().clone(),
+            precompile_calls: self.counter.precompile_calls.borrow().clone(),
+        }
     }
 
     pub(crate) fn reset(&self) {
-        let mut count = self.counter.lock().unwrap();
-        *count = [0; 256];
+        self.counter.count.iter().for_each(|count| count.set(0));
+        self.counter.input_sizes.borrow_mut().clear();
+        self.counter.current_frame.set(None);
+        *self.counter.frame_start.borrow_mut() = [0; 256];
+        self.counter.frames.borrow_mut().clear();
+        self.counter.calls.borrow_mut().clear();
+        self.counter.precompiles.set(None);
+        self.counter.precompile_calls.borrow_mut().clear();
+    }
+}
+
+impl Drop for InstructionCounterGuard<'_> {
+    fn drop(&mut self) {
+        self.counter.in_use.set(false);
+    }
+}
+
+impl PrecompileProvider<ContextT> for CountingPrecompiles {
+    type Output = InterpreterResult;
+
+    fn set_spec(&mut self, spec: SpecId) -> bool {
+        <EthPrecompiles as PrecompileProvider<ContextT>>::set_spec(&mut self.inner, spec)
+    }
+
+    fn run(
+        &mut self,
+        context: &mut ContextT,
+        address: &Address,
+        inputs: &InputsImpl,
+        is_static: bool,
+        gas_limit: u64,
+    ) -> Result<Option<InterpreterResult>, String> {
+        let output = self
+            .inner
+            .run(context, address, inputs, is_static, gas_limit)?;
+        if let Some(output) = &output {
+            let input = match &inputs.input {
+                CallInput::Bytes(bytes) => PrecompileInput::read(*address, bytes),
+                CallInput::SharedBuffer(range) => {
+                    let buffer = context.local.shared_memory_buffer.borrow();
+                    PrecompileInput::read(*address, buffer.get(range.clone()).unwrap_or_default())
+                }
+            };
+            INSTRUCTION_COUNTER.with(|c| c.count_precompile(*address, input, output.gas.spent()));
+        }
+        Ok(output)
+    }
+
+    fn warm_addresses(&self) -> Box<impl Iterator<Item = Address>> {
+        <EthPrecompiles as PrecompileProvider<ContextT>>::warm_addresses(&self.inner)
+    }
+
+    fn contains(&self, address: &Address) -> bool {
+        <EthPrecompiles as PrecompileProvider<ContextT>>::contains(&self.inner, address)
     }
 }
 
 impl OpcodeUsage {
     pub fn get(&self, op: OpCode) -> Option<usize> {
-        self.0.get(&op).copied()
+        self.counts.get(&op).copied()
     }
 
     pub fn iter(&self) -> impl Iterator<Item = (OpCode, usize)> + '_ {
-        self.0.iter().map(|(k, v)| (*k, *v))
+        self.counts.iter().map(|(k, v)| (*k, *v))
+    }
+
+    /// The histogram of the input sizes of a dynamic opcode, as `(input_size, count)` pairs.
+    pub fn input_sizes(&self, op: OpCode) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.input_sizes
+            .get(&op)
+            .into_iter()
+            .flatten()
+            .map(|(k, v)| (*k, *v))
+    }
+
+    /// The opcodes run in each frame, ordered by depth.
+    pub fn frames(&self) -> impl Iterator<Item = (Frame, &BTreeMap<OpCode, usize>)> + '_ {
+        self.frames.iter().map(|(frame, usage)| (*frame, usage))
+    }
+
+    /// Number of times the call opcode called into the kind of target.
+    pub fn calls(&self, op: OpCode, target: CallTarget) -> usize {
+        self.calls.get(&(op, target)).copied().unwrap_or_default()
+    }
+
+    /// The calls into precompiles, grouped by precompile address and input.
+    pub fn precompile_calls(
+        &self,
+    ) -> impl Iterator<Item = (Address, PrecompileInput, PrecompileCalls)> + '_ {
+        self.precompile_calls
+            .iter()
+            .map(|((address, input), calls)| (*address, *input, *calls))
+    }
+
+    /// Estimates the cycles of every execution of the opcode, evaluating the model on the input
+    /// size of each, or on 1 for executions without one as constant test vectors are measured.
+    pub fn estimate_cycle_count(&self, op: OpCode, model: &CycleModel) -> f64 {
+        let at = |input_size| model.estimate_cycle_count(&CycleParams::from_input_size(input_size));
+        let mut sized = 0;
+        let mut cycles = 0.0;
+        for (input_size, count) in self.input_sizes(op) {
+            sized += count;
+            cycles += at(input_size) * count as f64;
+        }
+        let unsized_count = self.get(op).unwrap_or_default().saturating_sub(sized);
+        cycles + at(1) * unsized_count as f64
     }
 }
+
+impl PrecompileInput {
+    fn read(address: Address, input: &[u8]) -> Self {
+        Self {
+            len: input.len(),
+            modexp_lengths: modexp_lengths(address, input),
+        }
+    }
+}
+
+/// Formats as `ADD:3;KECCAK256:2@32x1,64x1`, the form stored in result files, with the input size
+/// histogram of the dynamic opcodes after the `@`.
+impl Display for OpcodeUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, (op, count)) in self.iter().enumerate() {
+            if idx > 0 {
+                f.write_str(";")?;
+            }
+            write!(f, "{}:{count}", op.as_str())?;
+            for (idx, (input_size, count)) in self.input_sizes(op).enumerate() {
+                f.write_str(if idx == 0 { "@" } else { "," })?;
+                write!(f, "{input_size}x{count}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for OpcodeUsage {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut usage = OpcodeUsage::default();
+        for entry in s.split(';').filter(|entry| !entry.is_empty()) {
+            let (op, count) = entry
+                .split_once(':')
+                .ok_or_else(|| format!("invalid opcode usage entry {entry}"))?;
+            let op = OpCode::parse(op).ok_or_else(|| format!("unknown opcode {op}"))?;
+            let (count, input_sizes) = count.split_once('@').unwrap_or((count, ""));
+            let count = count
+                .parse()
+                .map_err(|_| format!("invalid opcode count {count}"))?;
+            usage.counts.insert(op, count);
+            for bucket in input_sizes.split(',').filter(|bucket| !bucket.is_empty()) {
+                let (input_size, count) = bucket
+                    .split_once('x')
+                    .and_then(|(input_size, count)| {
+                        Some((input_size.parse().ok()?, count.parse().ok()?))
+                    })
+                    .ok_or_else(|| format!("invalid input size bucket {bucket}"))?;
+                usage
+                    .input_sizes
+                    .entry(op)
+                    .or_default()
+                    .insert(input_size, count);
+            }
+        }
+        Ok(usage)
+    }
+}
+
+/// The input size of a dynamic opcode as its test vector measures it, read from the stack before
+/// the opcode executes.
+fn input_size(op: u8, stack: &Stack) -> Option<usize> {
+    let size_at = |n| stack.peek(n).ok().map(|size| size.saturating_to::<usize>());
+    match OpCode::new(op)? {
+        // the byte length of the exponent
+        OpCode::EXP => stack
+            .peek(1)
+            .ok()
+            .map(|exponent| exponent.bit_len().div_ceil(8)),
+        OpCode::KECCAK256
+        | OpCode::LOG0
+        | OpCode::LOG1
+        | OpCode::LOG2
+        | OpCode::LOG3
+        | OpCode::LOG4 => size_at(1),
+        OpCode::CALLDATACOPY | OpCode::CODECOPY | OpCode::RETURNDATACOPY | OpCode::MCOPY => {
+            size_at(2)
+        }
+        OpCode::EXTCODECOPY => size_at(3),
+        // the size of the call arguments
+        OpCode::CALL | OpCode::CALLCODE => size_at(4),
+        OpCode::DELEGATECALL | OpCode::STATICCALL => size_at(3),
+        _ => None,
+    }
+}
+
 const fn instruction_table() -> InstructionTableT {
     use evm_guest::{
         bytecode::opcode::*,
@@ -84,7 +463,7 @@ const fn instruction_table() -> InstructionTableT {
     macro_rules! wrap {
         ($op:expr, $inst:expr) => {
             table[$op as usize] = |interpreter: &mut InterpreterT, host: &mut ContextT| {
-                INSTRUCTION_COUNTER.with(|c| c.count($op));
+                INSTRUCTION_COUNTER.with(|c| c.count($op, interpreter, host));
                 $inst(interpreter, host)
             }
         };
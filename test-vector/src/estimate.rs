@@ -0,0 +1,264 @@
+//! Estimates the cycles of an execution from the cycle LUTs.
+
+use crate::{
+    LutRegistry, OpCodeOrPrecompile, OpcodeUsage,
+    counting::{CountingPrecompiles, INSTRUCTION_COUNTER, INSTRUCTION_TABLE_WITH_COUNTING},
+    filler::precompile::model_input,
+};
+use evm_guest::{
+    context::{BlockEnv, CfgEnv, TxEnv, result::ExecutionResult},
+    primitives::KECCAK_EMPTY,
+    *,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
+
+/// The opcodes an execution ran, with the input sizes of the dynamic ones.
+#[derive(Default, Debug, Clone)]
+pub struct ExecutionTrace {
+    pub usage: OpcodeUsage,
+}
+
+/// The estimated cycles of an execution.
+#[derive(Default, Debug, Clone)]
+pub struct CycleEstimate {
+    pub total: f64,
+    pub opcodes: BTreeMap<OpCode, OpcodeEstimate>,
+    /// Called precompiles by test vector name, the count is the number of calls.
+    pub precompiles: BTreeMap<Arc<str>, OpcodeEstimate>,
+    /// Executed opcodes and called precompiles without a cycle model, not part of the total.
+    /// Precompiles without a test vector are named by their address.
+    pub missing: BTreeSet<OpCodeOrPrecompile>,
+}
+
+#[derive(Default, Debug, Copy, Clone)]
+pub struct OpcodeEstimate {
+    pub count: usize,
+    pub cycles: f64,
+}
+
+/// A transaction to estimate, executed on top of the pre-state.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionFixture {
+    pub spec: SpecId,
+    #[serde(default)]
+    pub block: BlockEnv,
+    pub tx: TxEnv,
+    #[serde(default)]
+    pub pre_state: Cache,
+}
+
+impl LutRegistry {
+    /// Estimates the cycles of a trace, evaluating the model of a dynamic opcode on the input size
+    /// of every execution of it, and the model of a precompile on the input of every call.
+    pub fn estimate(&self, trace: &ExecutionTrace) -> CycleEstimate {
+        let mut estimate = CycleEstimate::default();
+        for (op, count) in trace.usage.iter() {
+            let Some(model) = self.opcode(op) else {
+                estimate.missing.insert(OpCodeOrPrecompile::OpCode(op));
+                continue;
+            };
+            let cycles = trace.usage.estimate_cycle_count(op, model);
+            estimate.total += cycles;
+            estimate
+                .opcodes
+                .insert(op, OpcodeEstimate { count, cycles });
+        }
+        for (address, input, calls) in trace.usage.precompile_calls() {
+            let Some((name, params)) = model_input(address, input) else {
+                estimate
+                    .missing
+                    .insert(OpCodeOrPrecompile::Precompile(address.to_string().into()));
+                continue;
+            };
+            let Some(model) = self.precompile(name) else {
+                estimate
+                    .missing
+                    .insert(OpCodeOrPrecompile::Precompile(name.into()));
+                continue;
+            };
+            let cycles = calls.count as f64 * model.estimate_cycle_count(&params);
+            estimate.total += cycles;
+            let precompile = estimate.precompiles.entry(name.into()).or_default();
+            precompile.count += calls.count;
+            precompile.cycles += cycles;
+        }
+        estimate
+    }
+}
+
+impl TransactionFixture {
+    pub fn context_builder(&self) -> ContextBuilder {
+        ContextBuilder {
+            block: self.block.clone(),
+            tx: self.tx.clone(),
+            cfg: CfgEnv {
+                spec: self.spec,
+                ..Default::default()
+            },
+            db: self.pre_state.clone(),
+            transient_storage: Default::default(),
+            shared_memory_buffer: Default::default(),
+        }
+    }
+
+    /// The same transaction with the code of every account removed, and an empty init code for
+    /// a create, measuring what the transaction costs besides its opcodes.
+    pub fn without_code(&self) -> Self {
+        let mut fixture = self.clone();
+        for account in fixture.pre_state.accounts.values_mut() {
+            account.info.code = Some(Bytecode::default());
+            account.info.code_hash = KECCAK_EMPTY;
+        }
+        fixture.pre_state.contracts.clear();
+        if fixture.tx.kind.is_create() {
+            fixture.tx.data = Bytes::new();
+        }
+        fixture
+    }
+
+    /// Executes the transaction with the counting instruction table.
+    pub fn trace(&self) -> (Result<ExecutionResult, EvmErrorT>, ExecutionTrace) {
+        INSTRUCTION_COUNTER.with(|counter| {
+            let guard = counter.lock();
+            guard.reset();
+
+            let instructions =
+                INSTRUCTION_TABLE_WITH_COUNTING.with(|table| EthInstructionsT::new(table.clone()));
+            let result = execute_transaction_with_precompiles(
+                self.spec,
+                &self.context_builder(),
+                instructions,
+                CountingPrecompiles::default(),
+            );
+            let trace = ExecutionTrace {
+                usage: guard.read(),
+            };
+            guard.reset();
+            (result, trace)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CycleLut, CycleModel, CycleParams, LutEntry, MODEXP_MODEL};
+    use revm_precompile::u64_to_address;
+
+    /// Calls the precompile at `address` directly from a transaction.
+    fn call_precompile(address: u64, input: Bytes) -> ExecutionTrace {
+        let caller = Address::repeat_byte(0xaa);
+        let mut pre_state = Cache::default();
+        pre_state
+            .accounts
+            .insert(caller, AccountInfo::from_balance(U256::MAX).into());
+        let fixture = TransactionFixture {
+            spec: SpecId::CANCUN,
+            block: BlockEnv::default(),
+            tx: TxEnv {
+                caller,
+                gas_limit: 1_000_000,
+                kind: TxKind::Call(u64_to_address(address)),
+                data: input,
+                ..Default::default()
+            },
+            pre_state,
+        };
+        let (result, trace) = fixture.trace();
+        assert!(result.unwrap().is_success());
+        trace
+    }
+
+    #[test]
+    fn estimate_precompile_calls() {
+        let mut precompiles = CycleLut::new();
+        precompiles.entries.insert(
+            "ecPairing".to_string(),
+            LutEntry {
+                model: CycleModel::Linear {
+                    slope: 1000.0,
+                    intercept: 100.0,
+                },
+                stats: None,
+                source_run: None,
+            },
+        );
+        let luts = LutRegistry::from_luts(&CycleLut::new(), &precompiles).unwrap();
+
+        // an empty pairing input is a valid call with no pairs
+        let estimate = luts.estimate(&call_precompile(0x08, Bytes::new()));
+        assert_eq!(estimate.total, 100.0);
+        assert_eq!(estimate.precompiles["ecPairing"].count, 1);
+        assert_eq!(estimate.precompiles["ecPairing"].cycles, 100.0);
+        assert!(estimate.missing.is_empty());
+
+        let estimate = luts.estimate(&call_precompile(0x06, Bytes::new()));
+        assert_eq!(estimate.total, 0.0);
+        assert!(
+            estimate
+                .missing
+                .contains(&OpCodeOrPrecompile::Precompile("ecAdd".into()))
+        );
+
+        let sha256 = u64_to_address(0x02);
+        let estimate = luts.estimate(&call_precompile(0x02, Bytes::new()));
+        assert!(estimate.precompiles.is_empty());
+        assert!(
+            estimate
+                .missing
+                .contains(&OpCodeOrPrecompile::Precompile(sha256.to_string().into()))
+        );
+    }
+
+    #[test]
+    fn estimate_modexp_on_lengths() {
+        let mut precompiles = CycleLut::new();
+        precompiles.entries.insert(
+            MODEXP_MODEL.to_string(),
+            LutEntry {
+                model: CycleModel::Multi {
+                    intercept: 10.0,
+                    terms: [
+                        (
+                            CycleParams::EXP_LEN.to_string(),
+                            CycleModel::Linear {
+                                slope: 1.0,
+                                intercept: 0.0,
+                            },
+                        ),
+                        (
+                            CycleParams::MOD_LEN.to_string(),
+                            CycleModel::Linear {
+                                slope: 5.0,
+                                intercept: 0.0,
+                            },
+                        ),
+                    ]
+                    .into(),
+                },
+                stats: None,
+                source_run: None,
+            },
+        );
+        let luts = LutRegistry::from_luts(&CycleLut::new(), &precompiles).unwrap();
+
+        // 2^3 mod 5 with a base of 1 byte, an exponent of 2 bytes and a modulus of 3 bytes
+        let mut input = vec![0; 96];
+        for (idx, len) in [1, 2, 3].into_iter().enumerate() {
+            input[idx * 32 + 31] = len;
+        }
+        input.extend([2, 0, 3, 0, 0, 5]);
+        let trace = call_precompile(0x05, input.into());
+        let (_, input, _) = trace.usage.precompile_calls().next().unwrap();
+        assert_eq!(input.modexp_lengths, Some([1, 2, 3]));
+
+        let estimate = luts.estimate(&trace);
+        assert_eq!(estimate.precompiles[MODEXP_MODEL].count, 1);
+        assert_eq!(estimate.total, 10.0 + 2.0 + 15.0);
+        assert!(estimate.missing.is_empty());
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{TestCaseBuilder, TestCaseKind};
+use crate::{CycleParams, TestCaseBuilder, TestCaseKind};
 use evm_guest::{primitives::bytes::BytesMut, *};
 use rand::{Rng, RngCore, SeedableRng};
 use rand_xoshiro::Xoshiro256Plus;
@@ -25,6 +25,9 @@ pub(crate) type ReturnDataBuilder = Box<dyn Fn(&mut BytesMut, BuilderParams) + S
 pub(crate) type BytecodeBuilder = Box<dyn Fn(BuilderParams) -> Bytecode + Send + Sync>;
 pub(crate) type InputBuilder = Box<dyn Fn(&mut BytesMut, BuilderParams) + Send + Sync>;
 pub(crate) type ContextBuilderFn = Box<dyn Fn(&mut ContextBuilder, BuilderParams) + Send + Sync>;
+pub(crate) type PrecompileCallBuilder =
+    Box<dyn Fn(BuilderParams) -> (Address, usize) + Send + Sync>;
+pub(crate) type CycleParamsBuilder = Box<dyn Fn(usize) -> CycleParams + Send + Sync>;
 
 const MAX_KECCAK_SIZE_LOG2: u32 = 14;
 const MAX_CALLDATA_SIZE_LOG2: u32 = 15;
@@ -150,6 +153,8 @@ impl Default for TestCaseBuilder {
             target_address: CALEE_ADDRESS,
             caller_address: CALLER_ADDRESS,
             call_value: U256::ZERO,
+            expected_precompile_call: None,
+            cycle_params: None,
             spec_id: SpecId::OSAKA,
         }
     }
@@ -1,6 +1,6 @@
 use crate::{
-    TestCaseBuilder, TestCaseKind,
-    filler::{StackBuilder, default_bytecode_with_pop_builder},
+    CycleParams, MODEXP_MODEL, PrecompileInput, TestCaseBuilder, TestCaseKind,
+    filler::{PrecompileCallBuilder, StackBuilder, default_bytecode_with_pop_builder},
 };
 use ark_ff::Field;
 use evm_guest::*;
@@ -33,6 +33,73 @@ pub(crate) fn fill(map: &mut BTreeMap<Arc<str>, Arc<TestCaseBuilder>>) {
     fill_ec_pair(map);
 }
 
+/// The LUT entry a call into a precompile is estimated with, and the parameters of its model for
+/// the call. Modexp costs depend on the lengths in its input rather than on the length of it, so
+/// it is estimated with [`MODEXP_MODEL`] on those lengths. `None` for other precompiles without a
+/// test vector.
+pub(crate) fn model_input(
+    address: Address,
+    input: PrecompileInput,
+) -> Option<(&'static str, CycleParams)> {
+    use bn128::*;
+
+    let (name, input_size) = match address {
+        EC_ADD_ADDR => ("ecAdd", 1),
+        // the bits of the scalar are not recorded, the widest scalar bounds the cycles
+        EC_MUL_ADDR => ("ecMul", 253),
+        EC_PAIRING_ADDR => ("ecPairing", input.len / PAIR_ELEMENT_LEN),
+        modexp::ADDR => {
+            let [base_len, exp_len, mod_len] = input.modexp_lengths?;
+            let params = CycleParams::new().with_modexp_lengths(base_len, exp_len, mod_len);
+            return Some((MODEXP_MODEL, params));
+        }
+        _ => return None,
+    };
+    Some((name, CycleParams::from_input_size(input_size)))
+}
+
+/// The base, exponent and modulus lengths in the head of the input of a modexp call, `None` for
+/// calls into other precompiles. A short input is read padded with zeros, as the precompile does.
+pub(crate) fn modexp_lengths(address: Address, input: &[u8]) -> Option<[usize; 3]> {
+    if address != modexp::ADDR {
+        return None;
+    }
+    let mut header = [0; modexp::HEADER_LEN];
+    let len = input.len().min(modexp::HEADER_LEN);
+    header[..len].copy_from_slice(&input[..len]);
+    Some(std::array::from_fn(|idx| {
+        U256::from_be_slice(&header[idx * 32..(idx + 1) * 32]).saturating_to()
+    }))
+}
+
+/// The precompile of a test vector and an input it charges the same gas for as the test vector
+/// does for `input_size`.
+pub(crate) fn gas_input(name: &str, input_size: usize) -> Option<(Address, Vec<u8>)> {
+    use bn128::*;
+
+    let modexp = |base_modulus_len: usize, exponent_len: usize| {
+        let mut input = vec![0; modexp::HEADER_LEN + 2 * base_modulus_len + exponent_len];
+        // the gas depends on the highest bit set of the exponent
+        modexp::write_input(
+            &mut input,
+            &vec![0; base_modulus_len],
+            &vec![0xff; exponent_len],
+            &vec![0; base_modulus_len],
+        );
+        (modexp::ADDR, input)
+    };
+    let input = match name {
+        "ecAdd" => (EC_ADD_ADDR, vec![0; ADD_INPUT_LEN]),
+        "ecMul" => (EC_MUL_ADDR, vec![0; MUL_INPUT_LEN]),
+        // points at infinity, priced the same as any other pair
+        "ecPairing" => (EC_PAIRING_ADDR, vec![0; PAIR_ELEMENT_LEN * input_size]),
+        "modexp-crt-bm" | "modexp-montgomery-bm" => modexp(input_size, 32),
+        "modexp-crt-e" | "modexp-montgomery-e" => modexp(32, input_size),
+        _ => return None,
+    };
+    Some(input)
+}
+
 fn fill_modexp_bm<F>(map: &mut BTreeMap<Arc<str>, Arc<TestCaseBuilder>>, name: &str, modulus_fn: F)
 where
     F: Fn(&mut Xoshiro256Plus, usize) -> Vec<u8> + Send + Sync + 'static,
@@ -45,6 +112,9 @@ where
     const E: [u8; E_SIZE] = [0xff; E_SIZE];
 
     let arg_size_fn = |bm_size| HEADER_LEN + bm_size * 2 + E_SIZE;
+    let cycle_params_fn = |bm_size| {
+        CycleParams::from_input_size(bm_size).with_modexp_lengths(bm_size, E_SIZE, bm_size)
+    };
 
     map.insert(
         name.clone(),
@@ -72,6 +142,8 @@ where
             }),
             stack_builder: call_stack_builder(ADDR, arg_size_fn, PRECOMPILE_CALL_MAX_GAS),
             bytecode_builder: default_bytecode_with_pop_builder(OpCode::DELEGATECALL),
+            expected_precompile_call: Some(expected_call(ADDR, arg_size_fn)),
+            cycle_params: Some(Box::new(cycle_params_fn)),
             ..Default::default()
         }),
     );
@@ -88,6 +160,9 @@ where
     const B_M_LENGTH: usize = 32; // length of B, M
 
     let arg_size_fn = |e_size| HEADER_LEN + B_M_LENGTH * 2 + e_size;
+    let cycle_params_fn = |e_size| {
+        CycleParams::from_input_size(e_size).with_modexp_lengths(B_M_LENGTH, e_size, B_M_LENGTH)
+    };
 
     map.insert(
         name.clone(),
@@ -116,6 +191,8 @@ where
             }),
             stack_builder: call_stack_builder(ADDR, arg_size_fn, PRECOMPILE_CALL_MAX_GAS),
             bytecode_builder: default_bytecode_with_pop_builder(OpCode::DELEGATECALL),
+            expected_precompile_call: Some(expected_call(ADDR, arg_size_fn)),
+            cycle_params: Some(Box::new(cycle_params_fn)),
             ..Default::default()
         }),
     );
@@ -158,6 +235,7 @@ fn fill_ec_add(map: &mut BTreeMap<Arc<str>, Arc<TestCaseBuilder>>) {
                 PRECOMPILE_CALL_MAX_GAS,
             ),
             bytecode_builder: default_bytecode_with_pop_builder(OpCode::DELEGATECALL),
+            expected_precompile_call: Some(expected_call(EC_ADD_ADDR, |_| ADD_INPUT_LEN)),
             ..Default::default()
         }),
     );
@@ -210,6 +288,7 @@ fn fill_ec_mul(map: &mut BTreeMap<Arc<str>, Arc<TestCaseBuilder>>) {
                 PRECOMPILE_CALL_MAX_GAS,
             ),
             bytecode_builder: default_bytecode_with_pop_builder(OpCode::DELEGATECALL),
+            expected_precompile_call: Some(expected_call(EC_MUL_ADDR, |_| MUL_INPUT_LEN)),
             ..Default::default()
         }),
     );
@@ -287,6 +366,7 @@ fn fill_ec_pair(map: &mut BTreeMap<Arc<str>, Arc<TestCaseBuilder>>) {
                 PRECOMPILE_CALL_MAX_GAS,
             ),
             bytecode_builder: default_bytecode_with_pop_builder(OpCode::DELEGATECALL),
+            expected_precompile_call: Some(expected_call(EC_PAIRING_ADDR, arg_size_fn)),
             ..Default::default()
         }),
     );
@@ -483,6 +563,13 @@ where
     })
 }
 
+fn expected_call<F>(addr: Address, arg_length_fn: F) -> PrecompileCallBuilder
+where
+    F: Fn(usize) -> usize + Send + Sync + 'static,
+{
+    Box::new(move |params| (addr, arg_length_fn(params.input_size)))
+}
+
 fn write_u256(buffer: &mut [u8], value: U256) -> &mut [u8] {
     assert!(buffer.len() >= 32);
     buffer[..32].copy_from_slice(&value.to_be_bytes::<32>());
@@ -498,6 +585,7 @@ fn write_slice<'a>(buffer: &'a mut [u8], slice: &[u8]) -> &'a mut [u8] {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::CallTarget;
 
     #[test]
     fn assert_repetition() {
@@ -507,8 +595,40 @@ mod tests {
         for (_, builder) in map.iter() {
             for tc in builder.build_all(Some(42)) {
                 let repetition = tc.repetition;
+                let (addr, input_len) = tc.expected_precompile_call().unwrap();
                 let opcodes = tc.count_opcodes();
                 assert_eq!(opcodes.get(OpCode::DELEGATECALL), Some(repetition));
+                assert_eq!(
+                    opcodes.calls(OpCode::DELEGATECALL, CallTarget::Precompile),
+                    repetition
+                );
+                let calls = opcodes
+                    .precompile_calls()
+                    .map(|(addr, input, calls)| (addr, input.len, calls.count))
+                    .collect::<Vec<_>>();
+                assert_eq!(calls, vec![(addr, input_len, repetition)]);
+            }
+        }
+    }
+
+    #[test]
+    fn modexp_cycle_params_match_calls() {
+        let mut map = BTreeMap::new();
+        fill(&mut map);
+
+        for (name, builder) in map.iter().filter(|(name, _)| name.starts_with("modexp")) {
+            for (repetition, input_size) in builder.params().take(3) {
+                let usage = builder
+                    .build(repetition, input_size, Some(42))
+                    .count_opcodes();
+                let (_, input, _) = usage.precompile_calls().next().unwrap();
+                let [base_len, exp_len, mod_len] = input.modexp_lengths.unwrap();
+                assert_eq!(
+                    builder.cycle_params(input_size),
+                    CycleParams::from_input_size(input_size)
+                        .with_modexp_lengths(base_len, exp_len, mod_len),
+                    "{name}"
+                );
             }
         }
     }
@@ -0,0 +1,416 @@
+//! Statistics used to fit cycle models from measurements.
+
+use clap::ValueEnum;
+use std::collections::BTreeMap;
+
+/// Two-sided 95% quantile of the standard normal distribution.
+const Z_95: f64 = 1.959_963_984_540_054;
+
+/// How the samples of a single input size are reduced to one value.
+#[derive(Default, Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum Aggregate {
+    /// The center of the most populated bin, bins are sized with the Freedman-Diaconis rule.
+    #[default]
+    Mode,
+    /// The median of the samples.
+    Median,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LinearFit {
+    pub slope: f64,
+    pub intercept: f64,
+    /// Coefficient of determination of the fit.
+    pub r2: f64,
+    /// Standard error of the slope, 0 when fitted through two points.
+    pub slope_stderr: f64,
+}
+
+impl Aggregate {
+    pub fn apply(self, values: &[f64]) -> f64 {
+        match self {
+            Aggregate::Mode => binned_mode(values, fd_bin_count(values)),
+            Aggregate::Median => median(values),
+        }
+    }
+}
+
+/// Returns the `q`-th quantile of the values, interpolating linearly between samples.
+pub fn quantile(values: &[f64], q: f64) -> f64 {
+    assert!(!values.is_empty(), "quantile of empty samples");
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let pos = q * (sorted.len() - 1) as f64;
+    let lo = pos.floor() as usize;
+    let hi = pos.ceil() as usize;
+    sorted[lo] + (sorted[hi] - sorted[lo]) * (pos - lo as f64)
+}
+
+pub fn median(values: &[f64]) -> f64 {
+    quantile(values, 0.5)
+}
+
+/// Sample standard deviation of the values around `center`, 0 for less than two values.
+pub fn stddev_around(values: &[f64], center: f64) -> f64 {
+    if values.len() < 2 {
+        return 0.0;
+    }
+    let ss = values.iter().map(|v| (v - center).powi(2)).sum::<f64>();
+    (ss / (values.len() - 1) as f64).sqrt()
+}
+
+/// The 95% confidence interval of an estimate, assuming normally distributed errors.
+pub fn confidence_interval(estimate: f64, stderr: f64) -> (f64, f64) {
+    (estimate - Z_95 * stderr, estimate + Z_95 * stderr)
+}
+
+/// Keeps the values between the `lo` and `hi` quantiles of all groups, inclusive. Groups left
+/// without values are dropped.
+pub fn clip_quantiles<K: Ord + Copy>(
+    groups: &BTreeMap<K, Vec<f64>>,
+    lo: f64,
+    hi: f64,
+) -> BTreeMap<K, Vec<f64>> {
+    let all = groups.values().flatten().copied().collect::<Vec<_>>();
+    let lo = quantile(&all, lo);
+    let hi = quantile(&all, hi);
+    groups
+        .iter()
+        .map(|(key, values)| {
+            let values = values
+                .iter()
+                .copied()
+                .filter(|v| (lo..=hi).contains(v))
+                .collect::<Vec<_>>();
+            (*key, values)
+        })
+        .filter(|(_, values)| !values.is_empty())
+        .collect()
+}
+
+/// Number of histogram bins by the Freedman-Diaconis rule, at least one.
+pub fn fd_bin_count(values: &[f64]) -> usize {
+    let iqr = quantile(values, 0.75) - quantile(values, 0.25);
+    let bin_width = 2.0 * iqr / (values.len() as f64).cbrt();
+    let (min, max) = min_max(values);
+    let bins = ((max - min) / bin_width).ceil();
+    if bins.is_finite() && bins >= 1.0 {
+        bins as usize
+    } else {
+        1
+    }
+}
+
+/// Returns the center of the most populated of `bins` equal width bins spanning the values.
+pub fn binned_mode(values: &[f64], bins: usize) -> f64 {
+    let (min, max) = min_max(values);
+    if min == max {
+        return min;
+    }
+    let width = (max - min) / bins as f64;
+    let mut counts = vec![0usize; bins];
+    for v in values {
+        let idx = (((v - min) / width) as usize).min(bins - 1);
+        counts[idx] += 1;
+    }
+    // the first bin wins a tie
+    let max_bin = counts
+        .iter()
+        .enumerate()
+        .rev()
+        .max_by_key(|(_, count)| **count)
+        .unwrap()
+        .0;
+    min + width * (max_bin as f64 + 0.5)
+}
+
+/// Ordinary least squares fit of `y = slope * x + intercept`.
+pub fn linear_fit(xs: &[f64], ys: &[f64]) -> LinearFit {
+    assert_eq!(xs.len(), ys.len());
+    assert!(xs.len() >= 2, "linear fit needs at least two points");
+    let n = xs.len() as f64;
+    let mean_x = xs.iter().sum::<f64>() / n;
+    let mean_y = ys.iter().sum::<f64>() / n;
+    let sxx = xs.iter().map(|x| (x - mean_x).powi(2)).sum::<f64>();
+    let sxy = xs
+        .iter()
+        .zip(ys)
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum::<f64>();
+    let slope = sxy / sxx;
+    let intercept = mean_y - slope * mean_x;
+
+    let ss_res = xs
+        .iter()
+        .zip(ys)
+        .map(|(x, y)| (y - (slope * x + intercept)).powi(2))
+        .sum::<f64>();
+    let ss_tot = ys.iter().map(|y| (y - mean_y).powi(2)).sum::<f64>();
+    let r2 = if ss_tot == 0.0 {
+        if ss_res == 0.0 { 1.0 } else { 0.0 }
+    } else {
+        1.0 - ss_res / ss_tot
+    };
+    let slope_stderr = if xs.len() > 2 {
+        (ss_res / (n - 2.0) / sxx).sqrt()
+    } else {
+        0.0
+    };
+
+    LinearFit {
+        slope,
+        intercept,
+        r2,
+        slope_stderr,
+    }
+}
+
+/// Accumulates the normal equations `AᵀA x = Aᵀb` of a least squares problem row by row.
+#[derive(Debug, Clone)]
+pub struct NormalEquations {
+    ata: Vec<Vec<f64>>,
+    atb: Vec<f64>,
+}
+
+impl NormalEquations {
+    pub fn new(variables: usize) -> Self {
+        Self {
+            ata: vec![vec![0.0; variables]; variables],
+            atb: vec![0.0; variables],
+        }
+    }
+
+    /// Adds the equation `Σ coefficient * x[idx] = rhs`, coefficients of a repeated index add up.
+    pub fn add_row(&mut self, row: &[(usize, f64)], rhs: f64) {
+        for &(i, a) in row {
+            self.atb[i] += a * rhs;
+            for &(j, b) in row {
+                self.ata[i][j] += a * b;
+            }
+        }
+    }
+
+    /// Solves for the non-negative `x` minimizing `|Ax - b|`, with the Lawson-Hanson active set
+    /// method run on the normal equations.
+    pub fn solve_nonnegative(&self) -> Vec<f64> {
+        let n = self.atb.len();
+        let scale = self
+            .ata
+            .iter()
+            .enumerate()
+            .map(|(i, row)| row[i])
+            .fold(0.0, f64::max);
+        let tolerance = 1e-10 * scale.max(1.0);
+
+        let mut x = vec![0.0; n];
+        let mut passive = vec![false; n];
+        for _ in 0..3 * n.max(1) {
+            let gradient = self.gradient(&x);
+            let Some(j) = (0..n)
+                .filter(|&j| !passive[j] && gradient[j] > tolerance)
+                .max_by(|&a, &b| gradient[a].total_cmp(&gradient[b]))
+            else {
+                break;
+            };
+            passive[j] = true;
+
+            loop {
+                let s = self.solve_passive(&passive);
+                if (0..n).all(|i| !passive[i] || s[i] > 0.0) {
+                    x = s;
+                    break;
+                }
+                // step towards s until the first passive variable hits zero
+                let alpha = (0..n)
+                    .filter(|&i| passive[i] && s[i] <= 0.0)
+                    .map(|i| x[i] / (x[i] - s[i]))
+                    .fold(1.0, f64::min);
+                for i in 0..n {
+                    x[i] += alpha * (s[i] - x[i]);
+                    if passive[i] && x[i] <= tolerance {
+                        passive[i] = false;
+                        x[i] = 0.0;
+                    }
+                }
+                if !passive.iter().any(|p| *p) {
+                    break;
+                }
+            }
+        }
+        x
+    }
+
+    /// Returns `Aᵀb - AᵀA x`, the descent direction of the residual.
+    fn gradient(&self, x: &[f64]) -> Vec<f64> {
+        self.ata
+            .iter()
+            .zip(&self.atb)
+            .map(|(row, atb)| atb - row.iter().zip(x).map(|(a, x)| a * x).sum::<f64>())
+            .collect()
+    }
+
+    /// Solves the unconstrained problem restricted to the passive variables, others are 0.
+    fn solve_passive(&self, passive: &[bool]) -> Vec<f64> {
+        let idx = (0..passive.len())
+            .filter(|&i| passive[i])
+            .collect::<Vec<_>>();
+        let mut m = idx
+            .iter()
+            .map(|&i| {
+                let mut row = idx.iter().map(|&j| self.ata[i][j]).collect::<Vec<_>>();
+                row.push(self.atb[i]);
+                row
+            })
+            .collect::<Vec<_>>();
+        let k = idx.len();
+
+        // gaussian elimination with partial pivoting, collinear variables are left at 0
+        let eps = 1e-12
+            * m.iter()
+                .enumerate()
+                .map(|(i, row)| row[i].abs())
+                .fold(f64::MIN_POSITIVE, f64::max);
+        let mut solution = vec![0.0; k];
+        let mut pivots = vec![usize::MAX; k];
+        let mut row = 0;
+        for col in 0..k {
+            let Some(pivot) = (row..k).max_by(|&a, &b| m[a][col].abs().total_cmp(&m[b][col].abs()))
+            else {
+                break;
+            };
+            if m[pivot][col].abs() <= eps {
+                continue;
+            }
+            m.swap(row, pivot);
+            for r in 0..k {
+                if r != row {
+                    let factor = m[r][col] / m[row][col];
+                    if factor != 0.0 {
+                        for c in col..=k {
+                            m[r][c] -= factor * m[row][c];
+                        }
+                    }
+                }
+            }
+            pivots[row] = col;
+            row += 1;
+        }
+        for (r, &col) in pivots.iter().enumerate().take(row) {
+            solution[col] = m[r][k] / m[r][col];
+        }
+
+        let mut x = vec![0.0; passive.len()];
+        for (i, value) in idx.into_iter().zip(solution) {
+            x[i] = value;
+        }
+        x
+    }
+}
+
+fn min_max(values: &[f64]) -> (f64, f64) {
+    values
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), v| {
+            (min.min(*v), max.max(*v))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quantiles_interpolate() {
+        let values = [4.0, 1.0, 3.0, 2.0];
+        assert_eq!(quantile(&values, 0.0), 1.0);
+        assert_eq!(quantile(&values, 1.0), 4.0);
+        assert_eq!(median(&values), 2.5);
+        assert_eq!(quantile(&values, 0.25), 1.75);
+    }
+
+    #[test]
+    fn clip_drops_outliers() {
+        let mut values = (0..100).map(f64::from).collect::<Vec<_>>();
+        values.push(10_000.0);
+        let clipped = clip_quantiles(&BTreeMap::from([(0, values)]), 0.05, 0.95);
+        assert!(!clipped[&0].contains(&10_000.0));
+        assert!(!clipped[&0].contains(&0.0));
+        assert_eq!(clipped[&0].len(), 91);
+
+        // the quantiles span all groups, a group of outliers is dropped
+        let groups = BTreeMap::from([(1, vec![1.0; 40]), (2, vec![2.0; 40]), (3, vec![100.0])]);
+        let clipped = clip_quantiles(&groups, 0.05, 0.95);
+        assert_eq!(clipped.keys().copied().collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(clipped[&1].len(), 40);
+    }
+
+    #[test]
+    fn mode_finds_the_peak() {
+        let mut values = vec![100.0; 50];
+        values.extend((0..20).map(|i| 100.0 + f64::from(i) * 10.0));
+        let mode = Aggregate::Mode.apply(&values);
+        assert!((mode - 100.0).abs() < 10.0, "{mode}");
+        assert_eq!(binned_mode(&[7.0, 7.0], 3), 7.0);
+        assert_eq!(fd_bin_count(&[7.0, 7.0]), 1);
+    }
+
+    #[test]
+    fn linear_fit_recovers_line() {
+        let xs = [1.0, 2.0, 4.0, 8.0];
+        let ys = xs.map(|x| 3.0 * x + 5.0);
+        let fit = linear_fit(&xs, &ys);
+        assert!((fit.slope - 3.0).abs() < 1e-9);
+        assert!((fit.intercept - 5.0).abs() < 1e-9);
+        assert!((fit.r2 - 1.0).abs() < 1e-9);
+        assert!(fit.slope_stderr < 1e-9);
+
+        let fit = linear_fit(&xs, &[8.0, 11.0, 17.0, 30.0]);
+        assert!(fit.slope_stderr > 0.0);
+        let (lo, hi) = confidence_interval(fit.slope, fit.slope_stderr);
+        assert!(lo < fit.slope && fit.slope < hi);
+    }
+
+    #[test]
+    fn nnls_solves_jointly() {
+        // x0 = 2, x1 = 3, x2 = 0
+        let rows: [(&[(usize, f64)], f64); 4] = [
+            (&[(0, 1.0)], 2.0),
+            (&[(0, 1.0), (1, 2.0)], 8.0),
+            (&[(1, 1.0), (2, 1.0)], 3.0),
+            (&[(0, 3.0), (1, 1.0), (2, 4.0)], 9.0),
+        ];
+        let mut equations = NormalEquations::new(3);
+        for (row, rhs) in rows {
+            equations.add_row(row, rhs);
+        }
+        let x = equations.solve_nonnegative();
+        for (x, expected) in x.iter().zip([2.0, 3.0, 0.0]) {
+            assert!((x - expected).abs() < 1e-9, "{x} != {expected}");
+        }
+
+        // the unconstrained solution is x0 = -1, clamped to 0
+        let mut equations = NormalEquations::new(2);
+        equations.add_row(&[(0, 1.0)], -1.0);
+        equations.add_row(&[(1, 1.0)], 4.0);
+        equations.add_row(&[(0, 1.0), (1, 1.0)], 3.0);
+        let x = equations.solve_nonnegative();
+        assert_eq!(x[0], 0.0);
+        assert!((x[1] - 3.5).abs() < 1e-9, "{}", x[1]);
+    }
+
+    #[test]
+    fn nnls_handles_collinear_variables() {
+        let mut equations = NormalEquations::new(2);
+        equations.add_row(&[(0, 1.0), (1, 1.0)], 4.0);
+        equations.add_row(&[(0, 2.0), (1, 2.0)], 8.0);
+        let x = equations.solve_nonnegative();
+        assert!((x[0] + x[1] - 4.0).abs() < 1e-9, "{x:?}");
+        assert!(x.iter().all(|x| *x >= 0.0));
+    }
+
+    #[test]
+    fn stddev_of_samples() {
+        assert_eq!(stddev_around(&[1.0], 0.0), 0.0);
+        assert_eq!(stddev_around(&[1.0, 3.0], 2.0), 2f64.sqrt());
+    }
+}
@@ -0,0 +1,343 @@
+//! The EVM gas schedule of the measured operations, in terms of the input size of their test
+//! vectors, taken from the gas functions of revm and its precompiles for the spec.
+//!
+//! Memory expansion is not included, neither is the cost of calling into a precompile.
+
+use crate::filler::precompile::gas_input;
+use clap::ValueEnum;
+use evm_guest::{OpCode, SpecId, U256, interpreter::gas};
+use revm_precompile::{PrecompileSpecId, Precompiles};
+
+/// Whether accounts and storage slots are accessed for the first time in the transaction.
+#[derive(Default, Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum Access {
+    #[default]
+    Warm,
+    Cold,
+}
+
+/// How a price in gas is rounded to the granularity.
+#[derive(Default, Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum Rounding {
+    #[default]
+    Nearest,
+    Up,
+    Down,
+}
+
+/// Rules turning cycles into a gas price.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PricingRules {
+    pub cycles_per_gas: f64,
+    pub rounding: Rounding,
+    /// Prices are rounded to a multiple of this.
+    pub granularity: u64,
+    /// Lowest price given.
+    pub floor: u64,
+    /// Relative difference to the mainnet price under which the mainnet price is kept.
+    pub tolerance: f64,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct GasCost {
+    /// The gas charged regardless of the input size.
+    pub static_gas: u64,
+    /// The gas charged for the input size.
+    pub dynamic_gas: u64,
+}
+
+impl GasCost {
+    const fn fixed(static_gas: u64) -> Self {
+        Self {
+            static_gas,
+            dynamic_gas: 0,
+        }
+    }
+
+    /// Splits the total gas of an operation charging `static_gas` regardless of its input.
+    fn split(static_gas: u64, total: u64) -> Self {
+        Self {
+            static_gas,
+            dynamic_gas: total.saturating_sub(static_gas),
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.static_gas + self.dynamic_gas
+    }
+}
+
+impl PricingRules {
+    /// Prices the cycles, keeping the mainnet price when it is within the tolerance.
+    pub fn price(&self, cycles: f64, mainnet: u64) -> u64 {
+        let raw = (cycles / self.cycles_per_gas).max(0.0);
+        if mainnet > 0 && (raw - mainnet as f64).abs() <= self.tolerance * mainnet as f64 {
+            return mainnet;
+        }
+        let granularity = self.granularity.max(1) as f64;
+        let units = raw / granularity;
+        let units = match self.rounding {
+            Rounding::Nearest => units.round(),
+            Rounding::Up => units.ceil(),
+            Rounding::Down => units.floor(),
+        };
+        ((units * granularity) as u64).max(self.floor)
+    }
+}
+
+/// Number of input size units the dynamic gas of an opcode is charged per, the word for the
+/// opcodes charged by word.
+pub fn dynamic_gas_unit(op: OpCode) -> usize {
+    match op {
+        OpCode::KECCAK256
+        | OpCode::CALLDATACOPY
+        | OpCode::CODECOPY
+        | OpCode::RETURNDATACOPY
+        | OpCode::EXTCODECOPY
+        | OpCode::MCOPY => 32,
+        _ => 1,
+    }
+}
+
+/// Returns the gas of an opcode, `input_size` is interpreted as its test vector does.
+///
+/// Returns `None` for opcodes not available in the spec.
+pub fn opcode_gas(op: OpCode, spec: SpecId, input_size: usize, access: Access) -> Option<GasCost> {
+    let size = input_size as u64;
+    let cold = access == Access::Cold;
+    let berlin = spec.is_enabled_in(SpecId::BERLIN);
+    let account_access = |istanbul: u64, tangerine: u64| {
+        if berlin {
+            gas::warm_cold_cost(cold)
+        } else if spec.is_enabled_in(SpecId::ISTANBUL) {
+            istanbul
+        } else if spec.is_enabled_in(SpecId::TANGERINE) {
+            tangerine
+        } else {
+            20
+        }
+    };
+
+    let available = match op {
+        OpCode::SHL | OpCode::SHR | OpCode::SAR | OpCode::EXTCODEHASH | OpCode::CREATE2 => {
+            spec.is_enabled_in(SpecId::CONSTANTINOPLE)
+        }
+        OpCode::RETURNDATASIZE | OpCode::RETURNDATACOPY | OpCode::STATICCALL | OpCode::REVERT => {
+            spec.is_enabled_in(SpecId::BYZANTIUM)
+        }
+        OpCode::CHAINID | OpCode::SELFBALANCE => spec.is_enabled_in(SpecId::ISTANBUL),
+        OpCode::BASEFEE => spec.is_enabled_in(SpecId::LONDON),
+        OpCode::PUSH0 => spec.is_enabled_in(SpecId::SHANGHAI),
+        OpCode::TLOAD | OpCode::TSTORE | OpCode::MCOPY | OpCode::BLOBHASH | OpCode::BLOBBASEFEE => {
+            spec.is_enabled_in(SpecId::CANCUN)
+        }
+        _ => true,
+    };
+    if !available {
+        return None;
+    }
+
+    let cost = match op {
+        OpCode::STOP | OpCode::RETURN | OpCode::REVERT | OpCode::INVALID => GasCost::fixed(0),
+        OpCode::JUMPDEST => GasCost::fixed(gas::JUMPDEST),
+        OpCode::ADDRESS
+        | OpCode::ORIGIN
+        | OpCode::CALLER
+        | OpCode::CALLVALUE
+        | OpCode::CALLDATASIZE
+        | OpCode::CODESIZE
+        | OpCode::GASPRICE
+        | OpCode::COINBASE
+        | OpCode::TIMESTAMP
+        | OpCode::NUMBER
+        | OpCode::DIFFICULTY
+        | OpCode::GASLIMIT
+        | OpCode::CHAINID
+        | OpCode::RETURNDATASIZE
+        | OpCode::POP
+        | OpCode::PC
+        | OpCode::MSIZE
+        | OpCode::GAS
+        | OpCode::BASEFEE
+        | OpCode::BLOBBASEFEE
+        | OpCode::PUSH0 => GasCost::fixed(gas::BASE),
+        OpCode::ADD
+        | OpCode::SUB
+        | OpCode::NOT
+        | OpCode::LT
+        | OpCode::GT
+        | OpCode::SLT
+        | OpCode::SGT
+        | OpCode::EQ
+        | OpCode::ISZERO
+        | OpCode::AND
+        | OpCode::OR
+        | OpCode::XOR
+        | OpCode::BYTE
+        | OpCode::SHL
+        | OpCode::SHR
+        | OpCode::SAR
+        | OpCode::CALLDATALOAD
+        | OpCode::MLOAD
+        | OpCode::MSTORE
+        | OpCode::MSTORE8
+        | OpCode::BLOBHASH => GasCost::fixed(gas::VERYLOW),
+        op if op.is_push() || (0x80..=0x9f).contains(&op.get()) => GasCost::fixed(gas::VERYLOW),
+        OpCode::MUL
+        | OpCode::DIV
+        | OpCode::SDIV
+        | OpCode::MOD
+        | OpCode::SMOD
+        | OpCode::SIGNEXTEND
+        | OpCode::SELFBALANCE => GasCost::fixed(gas::LOW),
+        OpCode::ADDMOD | OpCode::MULMOD | OpCode::JUMP => GasCost::fixed(gas::MID),
+        OpCode::JUMPI => GasCost::fixed(gas::HIGH),
+        // an exponent of `input_size` bytes
+        OpCode::EXP => GasCost::split(
+            gas::EXP,
+            gas::exp_cost(spec, U256::MAX >> (256 - 8 * input_size.min(32)))?,
+        ),
+        OpCode::KECCAK256 => GasCost::split(gas::KECCAK256, gas::keccak256_cost(input_size)?),
+        OpCode::CALLDATACOPY | OpCode::CODECOPY | OpCode::RETURNDATACOPY | OpCode::MCOPY => {
+            GasCost::split(gas::VERYLOW, gas::copy_cost_verylow(input_size)?)
+        }
+        OpCode::BALANCE => GasCost::fixed(account_access(700, 400)),
+        OpCode::EXTCODESIZE => GasCost::fixed(account_access(700, 700)),
+        OpCode::EXTCODEHASH => GasCost::fixed(account_access(700, 400)),
+        OpCode::EXTCODECOPY => GasCost::split(
+            account_access(700, 700),
+            gas::extcodecopy_cost(spec, input_size, cold)?,
+        ),
+        OpCode::BLOCKHASH => GasCost::fixed(gas::BLOCKHASH),
+        OpCode::SLOAD => GasCost::fixed(gas::sload_cost(spec, cold)),
+        // a write to a slot already dirty in the transaction
+        OpCode::SSTORE => GasCost::fixed(if berlin {
+            gas::WARM_STORAGE_READ_COST + if cold { gas::COLD_SLOAD_COST } else { 0 }
+        } else if spec.is_enabled_in(SpecId::ISTANBUL) {
+            gas::ISTANBUL_SLOAD_GAS
+        } else {
+            gas::SSTORE_RESET
+        }),
+        OpCode::TLOAD | OpCode::TSTORE => GasCost::fixed(gas::WARM_STORAGE_READ_COST),
+        OpCode::LOG0 | OpCode::LOG1 | OpCode::LOG2 | OpCode::LOG3 | OpCode::LOG4 => {
+            let topics = op.get() - OpCode::LOG0.get();
+            GasCost::split(
+                gas::LOG + gas::LOGTOPIC * topics as u64,
+                gas::log_cost(topics, size)?,
+            )
+        }
+        OpCode::CALL | OpCode::CALLCODE | OpCode::DELEGATECALL | OpCode::STATICCALL => {
+            GasCost::fixed(if spec.is_enabled_in(SpecId::TANGERINE) {
+                account_access(700, 700)
+            } else {
+                40
+            })
+        }
+        OpCode::CREATE | OpCode::CREATE2 => GasCost::fixed(gas::CREATE),
+        _ => return None,
+    };
+    Some(cost)
+}
+
+/// Returns the gas of a precompile test vector, `input_size` is interpreted as the test vector
+/// does. The gas is the one charged by the precompile of the spec for an input of that size, the
+/// static part is the gas charged for an input size of 0.
+///
+/// Returns `None` for unknown precompiles or ones not available in the spec.
+pub fn precompile_gas(name: &str, spec: SpecId, input_size: usize) -> Option<GasCost> {
+    let precompiles = Precompiles::new(PrecompileSpecId::from_spec_id(spec));
+    let gas_used = |input_size| {
+        let (address, input) = gas_input(name, input_size)?;
+        let run = precompiles.get(&address)?;
+        run(&input, u64::MAX).ok().map(|output| output.gas_used)
+    };
+    Some(GasCost::split(gas_used(0)?, gas_used(input_size)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opcode_gas_matches_schedule() {
+        let cancun = SpecId::CANCUN;
+        let at = |op, size| opcode_gas(op, cancun, size, Access::Warm).unwrap();
+        assert_eq!(at(OpCode::ADD, 0).total(), 3);
+        assert_eq!(at(OpCode::PUSH32, 0).total(), 3);
+        assert_eq!(at(OpCode::SWAP16, 0).total(), 3);
+        assert_eq!(at(OpCode::EXP, 2).total(), 10 + 100);
+        assert_eq!(at(OpCode::KECCAK256, 33).total(), 30 + 12);
+        assert_eq!(at(OpCode::LOG2, 10).total(), 375 * 3 + 80);
+        assert_eq!(at(OpCode::MCOPY, 64).dynamic_gas, 6);
+        assert_eq!(
+            opcode_gas(OpCode::SLOAD, cancun, 0, Access::Cold)
+                .unwrap()
+                .total(),
+            2100
+        );
+        assert_eq!(
+            opcode_gas(OpCode::SLOAD, SpecId::ISTANBUL, 0, Access::Warm)
+                .unwrap()
+                .total(),
+            800
+        );
+        assert!(opcode_gas(OpCode::MCOPY, SpecId::SHANGHAI, 0, Access::Warm).is_none());
+    }
+
+    #[test]
+    fn pricing_rules() {
+        let rules = PricingRules {
+            cycles_per_gas: 10.0,
+            rounding: Rounding::Nearest,
+            granularity: 1,
+            floor: 1,
+            tolerance: 0.1,
+        };
+        assert_eq!(rules.price(44.0, 0), 4);
+        // within 10% of mainnet
+        assert_eq!(rules.price(31.0, 3), 3);
+        assert_eq!(rules.price(60.0, 3), 6);
+        assert_eq!(rules.price(1.0, 0), 1);
+        assert_eq!(rules.price(-5.0, 0), 1);
+        let rules = PricingRules {
+            rounding: Rounding::Up,
+            granularity: 5,
+            floor: 0,
+            tolerance: 0.0,
+            ..rules
+        };
+        assert_eq!(rules.price(61.0, 5), 10);
+        assert_eq!(rules.price(0.0, 5), 0);
+    }
+
+    #[test]
+    fn precompile_gas_matches_schedule() {
+        let cancun = SpecId::CANCUN;
+        assert_eq!(precompile_gas("ecAdd", cancun, 0).unwrap().total(), 150);
+        assert_eq!(
+            precompile_gas("ecPairing", cancun, 2).unwrap().total(),
+            45_000 + 2 * 34_000
+        );
+        // 32 bytes operands, 255 iterations
+        assert_eq!(
+            precompile_gas("modexp-crt-bm", cancun, 32).unwrap().total(),
+            16 * 255 / 3
+        );
+        assert_eq!(
+            precompile_gas("modexp-crt-e", cancun, 1).unwrap().total(),
+            200
+        );
+        assert!(precompile_gas("unknown", cancun, 0).is_none());
+
+        assert_eq!(
+            precompile_gas("ecAdd", SpecId::BYZANTIUM, 0)
+                .unwrap()
+                .total(),
+            500
+        );
+        assert!(precompile_gas("ecAdd", SpecId::HOMESTEAD, 0).is_none());
+        let pairing = precompile_gas("ecPairing", cancun, 3).unwrap();
+        assert_eq!(pairing.static_gas, 45_000);
+        assert_eq!(pairing.dynamic_gas, 3 * 34_000);
+    }
+}
@@ -1,21 +1,27 @@
-use crate::counting::{INSTRUCTION_COUNTER, INSTRUCTION_TABLE_WITH_COUNTING};
+use crate::counting::{CountingPrecompiles, INSTRUCTION_COUNTER, INSTRUCTION_TABLE_WITH_COUNTING};
 use clap::ValueEnum;
 use evm_guest::*;
 use itertools::Itertools;
-use serde::Deserialize;
+use rand::{Rng, SeedableRng, seq::SliceRandom};
+use rand_xoshiro::Xoshiro256Plus;
+use serde::{Deserialize, Serialize};
 use std::{
     cell::RefCell,
     collections::{BTreeMap, BTreeSet},
     fmt::{Debug, Display},
     ops::{Deref, Range},
+    path::Path,
     rc::Rc,
     sync::{Arc, LazyLock},
 };
 
 mod counting;
+pub mod estimate;
 mod filler;
+pub mod fitting;
+pub mod gas;
 
-pub use counting::OpcodeUsage;
+pub use counting::{CallTarget, Frame, OpcodeUsage, PrecompileCalls, PrecompileInput};
 use evm_guest::primitives::bytes::BytesMut;
 
 pub static OPCODES_EXCLUDED: LazyLock<BTreeSet<OpCode>> = LazyLock::new(|| {
@@ -55,27 +61,260 @@ pub static OPCODES_EXCLUDED: LazyLock<BTreeSet<OpCode>> = LazyLock::new(|| {
     .into()
 });
 
-#[derive(Debug, Copy, Clone, Deserialize)]
-#[serde(untagged)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
 /// The opcode cycle model describes how to estimate the cycle count of an opcode.
 pub enum CycleModel {
     /// The cycle of this opcode is constant, irrelevant to input size.
-    Constant(f64),
+    Constant { cycles: f64 },
     /// The cycle of this opcode is linear, with a slope and intercept.
     Linear { slope: f64, intercept: f64 },
+    /// The cycle of this opcode is linear within each segment, for costs that step at
+    /// breakpoints.
+    PiecewiseLinear { segments: Vec<LinearSegment> },
+    /// The cycle of this opcode is a polynomial of the input size, lowest degree first.
+    Polynomial { coefficients: Vec<f64> },
+    /// The cycle of this opcode grows with `ln(1 + input_size)`.
+    LogLinear { log_slope: f64, intercept: f64 },
+    /// The cycle of this opcode depends on several named parameters, each term is evaluated on
+    /// its parameter as the input size. Modexp calls are evaluated on the lengths in their input,
+    /// see [`CycleParams::BASE_LEN`].
+    Multi {
+        intercept: f64,
+        terms: BTreeMap<String, CycleModel>,
+    },
 }
 
+/// A segment of a piecewise linear model, applying from its breakpoint up to the next one.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct LinearSegment {
+    pub from: f64,
+    pub slope: f64,
+    pub intercept: f64,
+}
+
+/// The named parameters a cycle model is evaluated on.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CycleParams(BTreeMap<String, f64>);
+
+/// The precompile LUT entry modexp calls are estimated with, a model of the lengths in the input
+/// of the call rather than of a single test vector.
+pub const MODEXP_MODEL: &str = "modexp";
+
+/// The version of the cycle LUT schema this crate reads and writes.
+pub const CYCLE_LUT_VERSION: u32 = 1;
+
+/// A cycle LUT file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CycleLut {
+    pub version: u32,
+    pub entries: BTreeMap<String, LutEntry>,
+}
+
+/// A fitted model of a single opcode or precompile.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LutEntry {
+    #[serde(flatten)]
+    pub model: CycleModel,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stats: Option<FitStats>,
+    /// Id of the run manifest of the results the model was fitted from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_run: Option<String>,
+}
+
+/// How well a model fits its samples.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct FitStats {
+    /// Number of samples the model was fitted from.
+    pub n: usize,
+    /// Coefficient of determination, only for models fitted over input sizes.
+    pub r2: Option<f64>,
+    /// Standard deviation of the samples around the model.
+    pub stddev: f64,
+    /// 95% confidence interval of the leading coefficient, the cycles of a constant model or the
+    /// slope of a linear one.
+    pub ci: (f64, f64),
+}
+
+/// The cycle LUTs used to estimate the cycles of opcodes and precompiles.
+#[derive(Debug, Clone)]
+pub struct LutRegistry {
+    opcodes: BTreeMap<OpCode, CycleModel>,
+    precompiles: BTreeMap<String, CycleModel>,
+}
+
+#[derive(Debug)]
+pub enum LutError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    UnsupportedVersion(u32),
+    UnknownOpcode(String),
+    UnknownPrecompile(String),
+    /// The segments of a piecewise linear model are not sorted by strictly increasing breakpoints.
+    UnsortedSegments(String),
+}
+
+impl CycleLut {
+    pub fn new() -> Self {
+        Self {
+            version: CYCLE_LUT_VERSION,
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Parses a LUT, rejecting other schema versions, unknown model kinds and piecewise linear
+    /// models with unsorted or overlapping segments.
+    pub fn parse(json: &str) -> Result<Self, LutError> {
+        let lut: CycleLut = serde_json::from_str(json).map_err(LutError::Json)?;
+        if lut.version != CYCLE_LUT_VERSION {
+            return Err(LutError::UnsupportedVersion(lut.version));
+        }
+        if let Some((name, _)) = lut
+            .entries
+            .iter()
+            .find(|(_, entry)| !entry.model.has_sorted_segments())
+        {
+            return Err(LutError::UnsortedSegments(name.clone()));
+        }
+        Ok(lut)
+    }
+
+    pub fn read(path: &Path) -> Result<Self, LutError> {
+        Self::parse(&std::fs::read_to_string(path).map_err(LutError::Io)?)
+    }
+
+    pub fn embedded_opcodes() -> Self {
+        Self::parse(include_str!("opcode-lut.json")).expect("Failed to parse opcode cycle LUT")
+    }
+
+    pub fn embedded_precompiles() -> Self {
+        Self::parse(include_str!("precompile-lut.json"))
+            .expect("Failed to parse precompile cycle LUT")
+    }
+}
+
+impl LutRegistry {
+    /// The LUTs embedded in this crate.
+    pub fn embedded() -> Self {
+        Self {
+            opcodes: OPCODE_CYCLE_LUT.clone(),
+            precompiles: PRECOMPILE_CYCLE_LUT.clone(),
+        }
+    }
+
+    /// Loads the LUTs from the given files, falling back to the embedded ones for missing paths.
+    pub fn load(
+        opcode_lut: Option<&Path>,
+        precompile_lut: Option<&Path>,
+    ) -> Result<Self, LutError> {
+        let opcodes = match opcode_lut {
+            Some(path) => CycleLut::read(path)?,
+            None => CycleLut::embedded_opcodes(),
+        };
+        let precompiles = match precompile_lut {
+            Some(path) => CycleLut::read(path)?,
+            None => CycleLut::embedded_precompiles(),
+        };
+        Self::from_luts(&opcodes, &precompiles)
+    }
+
+    pub fn from_luts(opcodes: &CycleLut, precompiles: &CycleLut) -> Result<Self, LutError> {
+        let opcodes = opcodes
+            .entries
+            .iter()
+            .map(|(name, entry)| {
+                let op =
+                    OpCode::parse(name).ok_or_else(|| LutError::UnknownOpcode(name.clone()))?;
+                Ok((op, entry.model.clone()))
+            })
+            .collect::<Result<_, LutError>>()?;
+        let precompiles = precompiles
+            .entries
+            .iter()
+            .map(|(name, entry)| {
+                if name != MODEXP_MODEL && !PRECOMPILE_TEST_VECTORS.contains_key(name.as_str()) {
+                    return Err(LutError::UnknownPrecompile(name.clone()));
+                }
+                Ok((name.clone(), entry.model.clone()))
+            })
+            .collect::<Result<_, LutError>>()?;
+        Ok(Self {
+            opcodes,
+            precompiles,
+        })
+    }
+
+    pub fn opcode(&self, op: OpCode) -> Option<&CycleModel> {
+        self.opcodes.get(&op)
+    }
+
+    pub fn precompile(&self, name: &str) -> Option<&CycleModel> {
+        self.precompiles.get(name)
+    }
+
+    pub fn contains_opcode(&self, op: OpCode) -> bool {
+        self.opcodes.contains_key(&op)
+    }
+
+    pub fn contains_precompile(&self, name: &str) -> bool {
+        self.precompiles.contains_key(name)
+    }
+
+    pub fn opcodes(&self) -> impl Iterator<Item = (OpCode, &CycleModel)> {
+        self.opcodes.iter().map(|(op, model)| (*op, model))
+    }
+
+    pub fn precompiles(&self) -> impl Iterator<Item = (&str, &CycleModel)> {
+        self.precompiles
+            .iter()
+            .map(|(name, model)| (name.as_str(), model))
+    }
+}
+
+impl Default for CycleLut {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Display for LutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LutError::Io(e) => write!(f, "failed to read LUT: {e}"),
+            LutError::Json(e) => write!(f, "invalid LUT: {e}"),
+            LutError::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported LUT version {version}, expected {CYCLE_LUT_VERSION}"
+            ),
+            LutError::UnknownOpcode(name) => write!(f, "unknown opcode {name} in LUT"),
+            LutError::UnknownPrecompile(name) => {
+                write!(f, "unknown precompile {name} in LUT, not a test vector")
+            }
+            LutError::UnsortedSegments(name) => write!(
+                f,
+                "segments of {name} in LUT are not sorted by strictly increasing breakpoints"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LutError {}
+
 pub static OPCODE_CYCLE_LUT: LazyLock<BTreeMap<OpCode, CycleModel>> = LazyLock::new(|| {
-    serde_json::from_str::<BTreeMap<String, CycleModel>>(include_str!("opcode-lut.json"))
-        .expect("Failed to parse opcode cycle LUT")
+    CycleLut::embedded_opcodes()
+        .entries
         .into_iter()
-        .map(|(k, v)| (k.parse().unwrap(), v))
+        .map(|(k, v)| (k.parse().unwrap(), v.model))
         .collect()
 });
 
 pub static PRECOMPILE_CYCLE_LUT: LazyLock<BTreeMap<String, CycleModel>> = LazyLock::new(|| {
-    serde_json::from_str::<BTreeMap<String, CycleModel>>(include_str!("precompile-lut.json"))
-        .expect("Failed to parse opcode cycle LUT")
+    CycleLut::embedded_precompiles()
+        .entries
+        .into_iter()
+        .map(|(k, v)| (k, v.model))
+        .collect()
 });
 
 pub static OPCODE_TEST_VECTORS: LazyLock<BTreeMap<OpCode, Arc<TestCaseBuilder>>> =
@@ -92,7 +331,7 @@ pub static PRECOMPILE_TEST_VECTORS: LazyLock<BTreeMap<Arc<str>, Arc<TestCaseBuil
         map
     });
 
-#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub enum OpCodeOrPrecompile {
     OpCode(OpCode),
     Precompile(Arc<str>),
@@ -111,6 +350,19 @@ pub enum TestCaseKind {
     DynamicMixed,
 }
 
+/// How the `(repetition, input_size)` pairs of a builder are chosen.
+#[derive(Default, Debug, Copy, Clone, Eq, PartialEq, Hash, ValueEnum)]
+pub enum Sampling {
+    /// Every pair, in order.
+    #[default]
+    Full,
+    /// A budget of pairs drawn uniformly without replacement.
+    Random,
+    /// A grid of repetition and input size strata with a budget of cells, one random pair drawn
+    /// from each, so that the whole range is covered.
+    Stratified,
+}
+
 pub struct TestCaseBuilder {
     /// the description of the test case
     description: Arc<str>,
@@ -135,6 +387,11 @@ pub struct TestCaseBuilder {
     caller_address: Address,
     call_value: U256,
 
+    /// the precompile and input length every repetition calls into, for precompile cases
+    expected_precompile_call: Option<filler::PrecompileCallBuilder>,
+    /// the parameters of the cycle model at an input size, for cases measuring several of them
+    cycle_params: Option<filler::CycleParamsBuilder>,
+
     spec_id: SpecId,
 }
 
@@ -144,20 +401,99 @@ pub struct TestCase {
     spec_id: SpecId,
     repetition: usize,
     input_size: usize,
+    random_seed: Option<u64>,
     interpreter: InterpreterT,
     context_builder: ContextBuilder,
+    expected_precompile_call: Option<(Address, usize)>,
 }
 
 impl CycleModel {
-    /// Returns the cycle counts for the given input size.
-    pub fn estimate_cycle_count(&self, input_size: usize) -> f64 {
+    /// Returns the cycle counts for the given parameters.
+    pub fn estimate_cycle_count(&self, params: &CycleParams) -> f64 {
+        let x = params.input_size();
+        match self {
+            CycleModel::Constant { cycles } => *cycles,
+            CycleModel::Linear { slope, intercept } => slope * x + intercept,
+            CycleModel::PiecewiseLinear { segments } => segments
+                .iter()
+                .rev()
+                .find(|segment| segment.from <= x)
+                .or(segments.first())
+                .map_or(0.0, |segment| segment.slope * x + segment.intercept),
+            CycleModel::Polynomial { coefficients } => coefficients
+                .iter()
+                .rev()
+                .fold(0.0, |acc, coefficient| acc * x + coefficient),
+            CycleModel::LogLinear {
+                log_slope,
+                intercept,
+            } => log_slope * x.ln_1p() + intercept,
+            CycleModel::Multi { intercept, terms } => {
+                intercept
+                    + terms
+                        .iter()
+                        .map(|(name, term)| {
+                            term.estimate_cycle_count(
+                                &CycleParams::new().with(CycleParams::INPUT_SIZE, params.get(name)),
+                            )
+                        })
+                        .sum::<f64>()
+            }
+        }
+    }
+
+    /// Whether the segments of piecewise linear models, including the terms of a multi model,
+    /// start at strictly increasing breakpoints, so each input size falls in a single segment.
+    fn has_sorted_segments(&self) -> bool {
         match self {
-            CycleModel::Constant(cycle) => *cycle,
-            CycleModel::Linear { slope, intercept } => slope * (input_size as f64) + intercept,
+            CycleModel::PiecewiseLinear { segments } => {
+                segments.windows(2).all(|pair| pair[0].from < pair[1].from)
+            }
+            CycleModel::Multi { terms, .. } => terms.values().all(Self::has_sorted_segments),
+            _ => true,
         }
     }
 }
 
+impl CycleParams {
+    /// The parameter single input models are evaluated on.
+    pub const INPUT_SIZE: &'static str = "input_size";
+    /// The byte lengths of the base, exponent and modulus of a modexp call.
+    pub const BASE_LEN: &'static str = "base_len";
+    pub const EXP_LEN: &'static str = "exp_len";
+    pub const MOD_LEN: &'static str = "mod_len";
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parameters with only the input size set.
+    pub fn from_input_size(input_size: usize) -> Self {
+        Self::new().with(Self::INPUT_SIZE, input_size as f64)
+    }
+
+    pub fn with(mut self, name: impl Into<String>, value: f64) -> Self {
+        self.0.insert(name.into(), value);
+        self
+    }
+
+    /// Sets the base, exponent and modulus lengths of a modexp call.
+    pub fn with_modexp_lengths(self, base_len: usize, exp_len: usize, mod_len: usize) -> Self {
+        self.with(Self::BASE_LEN, base_len as f64)
+            .with(Self::EXP_LEN, exp_len as f64)
+            .with(Self::MOD_LEN, mod_len as f64)
+    }
+
+    /// Returns the value of the parameter, 0 if it is not set.
+    pub fn get(&self, name: &str) -> f64 {
+        self.0.get(name).copied().unwrap_or_default()
+    }
+
+    pub fn input_size(&self) -> f64 {
+        self.get(Self::INPUT_SIZE)
+    }
+}
+
 impl OpCodeOrPrecompile {
     pub fn as_str(&self) -> &str {
         match self {
@@ -190,73 +526,180 @@ impl TestCaseBuilder {
         self.kind
     }
 
+    pub fn spec_id(&self) -> SpecId {
+        self.spec_id
+    }
+
     pub fn testcases_len(&self) -> usize {
         self.support_repetition.len() * self.support_input_size.len()
     }
 
-    pub fn build_all(&self, random_seed: Option<u64>) -> impl Iterator<Item = TestCase> + '_ {
+    pub fn input_sizes(&self) -> &[usize] {
+        &self.support_input_size
+    }
+
+    /// The parameters the cycle model of this test vector is evaluated on at the input size.
+    pub fn cycle_params(&self, input_size: usize) -> CycleParams {
+        match &self.cycle_params {
+            Some(cycle_params) => cycle_params(input_size),
+            None => CycleParams::from_input_size(input_size),
+        }
+    }
+
+    /// Lists the `(repetition, input_size)` pairs supported by this builder.
+    pub fn params(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
         self.support_repetition
             .clone()
-            .into_iter()
             .cartesian_product(self.support_input_size.iter().copied())
-            .filter_map(move |(repetition, input_size)| {
-                let params = filler::BuilderParams {
-                    repetition,
-                    input_size,
-                    random_seed,
-                };
-
-                let shared_memory_buffer = Rc::new(RefCell::new(Vec::new()));
-                let mut shared_memory = SharedMemory::new_with_buffer(shared_memory_buffer.clone());
-                (self.memory_builder)(&mut shared_memory, params);
-                let mut stack = Stack::new();
-                (self.stack_builder)(&mut stack, params);
-                let bytecode = (self.bytecode_builder)(params);
-                let mut return_data = BytesMut::default();
-                (self.return_data_builder)(&mut return_data, params);
-                let return_data = return_data.freeze();
-
-                let mut input = BytesMut::default();
-                (self.input_builder)(&mut input, params);
-                let input = input.freeze();
-                let inputs = InputsImpl {
-                    target_address: self.target_address,
-                    caller_address: self.caller_address,
-                    input: CallInput::Bytes(input.into()),
-                    call_value: self.call_value,
-                    ..Default::default()
-                };
-
-                let mut context_builder = ContextBuilder::new(
-                    self.caller_address,
-                    self.target_address,
-                    bytecode.clone(),
-                    shared_memory_buffer,
-                );
-                (self.context_builder)(&mut context_builder, params);
-
-                let mut interpreter = InterpreterT::new(
-                    shared_memory,
-                    ExtBytecode::new(bytecode),
-                    inputs,
-                    false,
-                    false,
-                    self.spec_id,
-                    u64::MAX,
-                );
-                interpreter.stack = stack;
-                interpreter.return_data.set_buffer(return_data.into());
-
-                Some(TestCase {
-                    description: self.description.clone(),
-                    kind: self.kind,
-                    spec_id: self.spec_id,
-                    repetition,
-                    input_size,
-                    interpreter,
-                    context_builder,
-                })
+    }
+
+    /// Number of pairs [`Self::sample_params`] yields, whatever the seed.
+    pub fn sample_len(&self, sampling: Sampling, budget: usize) -> usize {
+        match sampling {
+            Sampling::Full => self.testcases_len(),
+            Sampling::Random => budget.min(self.testcases_len()),
+            Sampling::Stratified => {
+                let (repetition_strata, input_size_strata) = self.strata(budget);
+                repetition_strata * input_size_strata
+            }
+        }
+    }
+
+    /// Chooses the `(repetition, input_size)` pairs to measure, the random strategies yield them
+    /// shuffled so that any prefix is spread over the whole range.
+    pub fn sample_params(
+        &self,
+        sampling: Sampling,
+        budget: usize,
+        seed: u64,
+    ) -> Vec<(usize, usize)> {
+        let mut rng = Xoshiro256Plus::seed_from_u64(seed);
+        let repetitions = self.support_repetition.clone();
+        let input_sizes = &self.support_input_size;
+        match sampling {
+            Sampling::Full => self.params().collect(),
+            Sampling::Random => rand::seq::index::sample(
+                &mut rng,
+                self.testcases_len(),
+                self.sample_len(sampling, budget),
+            )
+            .into_iter()
+            .map(|idx| {
+                (
+                    repetitions.start + idx / input_sizes.len(),
+                    input_sizes[idx % input_sizes.len()],
+                )
             })
+            .collect(),
+            Sampling::Stratified => {
+                let (repetition_strata, input_size_strata) = self.strata(budget);
+                // the i-th of n strata of a range of len
+                let stratum = |len: usize, n: usize, i: usize| i * len / n..(i + 1) * len / n;
+                let mut params = (0..repetition_strata)
+                    .cartesian_product(0..input_size_strata)
+                    .map(|(i, j)| {
+                        let repetition =
+                            rng.random_range(stratum(repetitions.len(), repetition_strata, i));
+                        let input_size =
+                            rng.random_range(stratum(input_sizes.len(), input_size_strata, j));
+                        (repetitions.start + repetition, input_sizes[input_size])
+                    })
+                    .collect_vec();
+                params.shuffle(&mut rng);
+                params
+            }
+        }
+    }
+
+    /// Number of repetition and input size strata of the stratified sampling, the budget is split
+    /// evenly between both when there are several input sizes.
+    fn strata(&self, budget: usize) -> (usize, usize) {
+        let budget = budget.max(1);
+        let input_size_strata = match self.support_input_size.len() {
+            0 | 1 => self.support_input_size.len(),
+            len => len.min(budget.isqrt()),
+        };
+        let repetition_strata = self
+            .support_repetition
+            .len()
+            .min(budget / input_size_strata.max(1));
+        (repetition_strata, input_size_strata)
+    }
+
+    pub fn build_all(&self, random_seed: Option<u64>) -> impl Iterator<Item = TestCase> + '_ {
+        self.params()
+            .map(move |(repetition, input_size)| self.build(repetition, input_size, random_seed))
+    }
+
+    /// Builds the single test case for the given parameters, the same one [`Self::build_all`]
+    /// yields for them with the same seed.
+    pub fn build(
+        &self,
+        repetition: usize,
+        input_size: usize,
+        random_seed: Option<u64>,
+    ) -> TestCase {
+        let params = filler::BuilderParams {
+            repetition,
+            input_size,
+            random_seed,
+        };
+
+        let shared_memory_buffer = Rc::new(RefCell::new(Vec::new()));
+        let mut shared_memory = SharedMemory::new_with_buffer(shared_memory_buffer.clone());
+        (self.memory_builder)(&mut shared_memory, params);
+        let mut stack = Stack::new();
+        (self.stack_builder)(&mut stack, params);
+        let bytecode = (self.bytecode_builder)(params);
+        let mut return_data = BytesMut::default();
+        (self.return_data_builder)(&mut return_data, params);
+        let return_data = return_data.freeze();
+
+        let mut input = BytesMut::default();
+        (self.input_builder)(&mut input, params);
+        let input = input.freeze();
+        let inputs = InputsImpl {
+            target_address: self.target_address,
+            caller_address: self.caller_address,
+            input: CallInput::Bytes(input.into()),
+            call_value: self.call_value,
+            ..Default::default()
+        };
+
+        let mut context_builder = ContextBuilder::new(
+            self.caller_address,
+            self.target_address,
+            bytecode.clone(),
+            shared_memory_buffer,
+        );
+        (self.context_builder)(&mut context_builder, params);
+
+        let mut interpreter = InterpreterT::new(
+            shared_memory,
+            ExtBytecode::new(bytecode),
+            inputs,
+            false,
+            false,
+            self.spec_id,
+            u64::MAX,
+        );
+        interpreter.stack = stack;
+        interpreter.return_data.set_buffer(return_data.into());
+
+        TestCase {
+            description: self.description.clone(),
+            kind: self.kind,
+            spec_id: self.spec_id,
+            repetition,
+            input_size,
+            random_seed,
+            interpreter,
+            context_builder,
+            expected_precompile_call: self
+                .expected_precompile_call
+                .as_ref()
+                .map(|expected| expected(params)),
+        }
     }
 }
 
@@ -283,6 +726,12 @@ impl TestCase {
         self.input_size
     }
 
+    /// the seed the case was built with, rebuilding it with the same parameters and seed
+    /// yields the same case
+    pub fn seed(&self) -> Option<u64> {
+        self.random_seed
+    }
+
     pub fn interpreter(&self) -> &InterpreterT {
         &self.interpreter
     }
@@ -295,37 +744,36 @@ impl TestCase {
         &self.context_builder
     }
 
+    /// The precompile and input length every repetition calls into, for precompile cases.
+    pub fn expected_precompile_call(&self) -> Option<(Address, usize)> {
+        self.expected_precompile_call
+    }
+
     pub fn count_opcodes(self) -> OpcodeUsage {
+        self.trace().1.usage
+    }
+
+    /// Executes the case on the host with the counting instruction table, returning the result of
+    /// the frame along with the trace.
+    pub fn trace(self) -> (InterpreterResult, estimate::ExecutionTrace) {
         INSTRUCTION_COUNTER.with(|counter| {
             let guard = counter.lock();
             guard.reset();
 
-            let context = self.context_builder.build(self.spec_id);
             let instructions =
                 INSTRUCTION_TABLE_WITH_COUNTING.with(|table| EthInstructionsT::new(table.clone()));
-
-            let mut evm = EvmT::new(context, instructions, EthPrecompiles::default());
-
-            let mut handler = HANDLER;
-
-            let first_frame_input = handler.first_frame_input(&mut evm, u64::MAX).unwrap();
-            let first_frame = handler
-                .first_frame_init(&mut evm, first_frame_input)
-                .unwrap();
-
-            let mut frame_result = match first_frame {
-                ItemOrResult::Item(mut frame) => {
-                    frame.interpreter = self.interpreter;
-                    handler.run_exec_loop(&mut evm, frame).unwrap()
-                }
-                ItemOrResult::Result(_) => unreachable!("case not expected"),
+            let frame_result = execute_with_precompiles(
+                self.spec_id,
+                self.interpreter,
+                &self.context_builder,
+                instructions,
+                CountingPrecompiles::default(),
+            );
+            let trace = estimate::ExecutionTrace {
+                usage: guard.read(),
             };
-            handler
-                .last_frame_result(&mut evm, &mut frame_result)
-                .unwrap();
-            let usage = guard.read();
             guard.reset();
-            usage
+            (frame_result.interpreter_result().clone(), trace)
         })
     }
 }
@@ -474,4 +922,233 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn estimate_cycle_models() {
+        let lut = CycleLut::parse(
+            r#"{"version": 1, "entries": {
+                "PAIRING": {"kind": "piecewise_linear", "segments": [
+                    {"from": 0, "slope": 0, "intercept": 100},
+                    {"from": 2, "slope": 10, "intercept": 80}
+                ]},
+                "POLY": {"kind": "polynomial", "coefficients": [1, 2, 3]},
+                "LOG": {"kind": "log_linear", "log_slope": 2, "intercept": 1},
+                "MODEXP": {"kind": "multi", "intercept": 5, "terms": {
+                    "base_len": {"kind": "linear", "slope": 2, "intercept": 0},
+                    "exp_len": {"kind": "constant", "cycles": 3}
+                }}
+            }}"#,
+        )
+        .unwrap();
+        let at = |name: &str, x: usize| {
+            lut.entries[name]
+                .model
+                .estimate_cycle_count(&CycleParams::from_input_size(x))
+        };
+        assert_eq!(at("PAIRING", 1), 100.0);
+        assert_eq!(at("PAIRING", 4), 120.0);
+        assert_eq!(at("POLY", 2), 17.0);
+        assert_eq!(at("LOG", 0), 1.0);
+        let params = CycleParams::new()
+            .with("base_len", 32.0)
+            .with("exp_len", 4.0);
+        assert_eq!(
+            lut.entries["MODEXP"].model.estimate_cycle_count(&params),
+            5.0 + 64.0 + 3.0
+        );
+    }
+
+    #[test]
+    fn parse_cycle_lut() {
+        let lut = CycleLut::parse(
+            r#"{"version": 1, "entries": {
+                "ADD": {"kind": "constant", "cycles": 100.0, "source_run": "0123456789abcdef",
+                    "stats": {"n": 10, "r2": null, "stddev": 1.5, "ci": [99.0, 101.0]}},
+                "MCOPY": {"kind": "linear", "slope": 2.0, "intercept": 50.0}
+            }}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            lut.entries["ADD"].source_run.as_deref(),
+            Some("0123456789abcdef")
+        );
+        assert_eq!(lut.entries["ADD"].stats.unwrap().n, 10);
+
+        let json = serde_json::to_string(&lut).unwrap();
+        let lut = CycleLut::parse(&json).unwrap();
+        assert_eq!(lut.entries["ADD"].stats.unwrap().ci, (99.0, 101.0));
+        assert_eq!(
+            lut.entries["MCOPY"]
+                .model
+                .estimate_cycle_count(&CycleParams::from_input_size(10)),
+            70.0
+        );
+
+        assert!(matches!(
+            CycleLut::parse(r#"{"version": 2, "entries": {}}"#),
+            Err(LutError::UnsupportedVersion(2))
+        ));
+        assert!(matches!(
+            CycleLut::parse(r#"{"version": 1, "entries": {"ADD": {"kind": "cubic"}}}"#),
+            Err(LutError::Json(_))
+        ));
+        assert!(matches!(
+            CycleLut::parse(r#"{"version": 1, "entries": {"ADD": 100.0}}"#),
+            Err(LutError::Json(_))
+        ));
+
+        assert!(!OPCODE_CYCLE_LUT.is_empty());
+        assert!(!PRECOMPILE_CYCLE_LUT.is_empty());
+    }
+
+    #[test]
+    fn reject_unsorted_segments() {
+        // a piecewise linear term of a multi model, starting at each breakpoint
+        let parse = |breakpoints: &[u32]| {
+            let segments = breakpoints
+                .iter()
+                .map(|from| format!(r#"{{"from": {from}, "slope": 1, "intercept": 0}}"#))
+                .join(",");
+            CycleLut::parse(&format!(
+                r#"{{"version": 1, "entries": {{"MCOPY": {{"kind": "multi", "intercept": 0,
+                    "terms": {{"input_size": {{"kind": "piecewise_linear",
+                        "segments": [{segments}]}}}}}}}}}}"#
+            ))
+        };
+        assert!(parse(&[0, 8]).is_ok());
+        for breakpoints in [[8, 0], [0, 0]] {
+            assert!(matches!(
+                parse(&breakpoints),
+                Err(LutError::UnsortedSegments(name)) if name == "MCOPY"
+            ));
+        }
+    }
+
+    #[test]
+    fn load_lut_registry() {
+        let registry = LutRegistry::load(None, None).unwrap();
+        assert_eq!(
+            registry.contains_opcode(OpCode::ADD),
+            OPCODE_CYCLE_LUT.contains_key(&OpCode::ADD)
+        );
+
+        // unique per process, concurrent test runs do not overwrite each other's LUT
+        let path = std::env::temp_dir().join(format!(
+            "test-vector-load-lut-registry-{}.json",
+            std::process::id()
+        ));
+        std::fs::write(
+            &path,
+            r#"{"version": 1, "entries": {"ADD": {"kind": "constant", "cycles": 1.0}}}"#,
+        )
+        .unwrap();
+        let registry = LutRegistry::load(Some(&path), None).unwrap();
+        assert!(registry.contains_opcode(OpCode::ADD));
+        assert!(!registry.contains_opcode(OpCode::MUL));
+        assert_eq!(
+            registry.contains_precompile("ecAdd"),
+            PRECOMPILE_CYCLE_LUT.contains_key("ecAdd")
+        );
+
+        std::fs::write(
+            &path,
+            r#"{"version": 1, "entries": {"NOPE": {"kind": "constant", "cycles": 1.0}}}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            LutRegistry::load(Some(&path), None),
+            Err(LutError::UnknownOpcode(_))
+        ));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn precompile_lut_names_test_vectors() {
+        for name in CycleLut::embedded_precompiles().entries.keys() {
+            assert!(
+                PRECOMPILE_TEST_VECTORS.contains_key(name.as_str()),
+                "{name} is not a precompile test vector"
+            );
+        }
+
+        let mut precompiles = CycleLut::new();
+        precompiles.entries.insert(
+            "ecParing".to_string(),
+            LutEntry {
+                model: CycleModel::Constant { cycles: 1.0 },
+                stats: None,
+                source_run: None,
+            },
+        );
+        assert!(matches!(
+            LutRegistry::from_luts(&CycleLut::new(), &precompiles),
+            Err(LutError::UnknownPrecompile(_))
+        ));
+    }
+
+    #[test]
+    fn opcode_usage_round_trip() {
+        let usage = OPCODE_TEST_VECTORS[&OpCode::ADD]
+            .build_all(Some(42))
+            .next()
+            .unwrap()
+            .count_opcodes();
+        let parsed = usage.to_string().parse::<OpcodeUsage>().unwrap();
+        assert_eq!(parsed.iter().collect_vec(), usage.iter().collect_vec());
+        assert!("".parse::<OpcodeUsage>().unwrap().iter().next().is_none());
+        assert!("NOPE:1".parse::<OpcodeUsage>().is_err());
+        assert!("KECCAK256:1@32".parse::<OpcodeUsage>().is_err());
+    }
+
+    #[test]
+    fn trace_records_input_sizes() {
+        let (result, trace) = OPCODE_TEST_VECTORS[&OpCode::KECCAK256]
+            .build(3, 64, Some(42))
+            .trace();
+        assert!(result.result.is_ok());
+        assert_eq!(
+            trace.usage.input_sizes(OpCode::KECCAK256).collect_vec(),
+            vec![(64, 3)]
+        );
+        assert!(trace.usage.input_sizes(OpCode::ISZERO).next().is_none());
+        let parsed = trace.usage.to_string().parse::<OpcodeUsage>().unwrap();
+        assert_eq!(
+            parsed.input_sizes(OpCode::KECCAK256).collect_vec(),
+            vec![(64, 3)]
+        );
+
+        let estimate = LutRegistry::embedded().estimate(&trace);
+        assert_eq!(estimate.opcodes[&OpCode::KECCAK256].count, 3);
+        assert!(estimate.total > 0.0);
+        // STOP is part of the baseline, it has no model
+        assert!(estimate.missing.iter().all(|op| *op == OpCode::STOP));
+    }
+
+    #[test]
+    fn sample_params() {
+        let builder = &OPCODE_TEST_VECTORS[&OpCode::EXP];
+        let all = builder.params().collect::<BTreeSet<_>>();
+        assert_eq!(
+            builder.sample_params(Sampling::Full, 0, 42),
+            builder.params().collect_vec()
+        );
+
+        for sampling in [Sampling::Random, Sampling::Stratified] {
+            let params = builder.sample_params(sampling, 64, 42);
+            assert_eq!(params.len(), builder.sample_len(sampling, 64));
+            assert!(params.len() <= 64 && params.len() > 32);
+            assert!(params.iter().all(|param| all.contains(param)));
+            assert_eq!(params.iter().unique().count(), params.len());
+            assert_eq!(params, builder.sample_params(sampling, 64, 42));
+        }
+
+        // the first and last of the 8 input size strata are covered
+        let input_sizes = builder
+            .sample_params(Sampling::Stratified, 64, 7)
+            .into_iter()
+            .map(|(_, input_size)| input_size)
+            .collect::<BTreeSet<_>>();
+        assert!(*input_sizes.first().unwrap() < 4);
+        assert!(*input_sizes.last().unwrap() >= 28);
+    }
 }
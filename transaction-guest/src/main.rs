@@ -0,0 +1,13 @@
+#![no_main]
+sp1_zkvm::entrypoint!(main);
+
+use evm_guest::*;
+
+pub fn main() {
+    let spec_id: SpecId = sp1_zkvm::io::read();
+    let context_builder: ContextBuilder = sp1_zkvm::io::read();
+
+    let result = execute_transaction(spec_id, &context_builder, EthInstructions::new_mainnet());
+
+    sp1_zkvm::io::commit(&result.ok());
+}